@@ -0,0 +1,123 @@
+// throws random levels and tile layouts at the simulation looking for panics
+// errors from the simulation are fine, they are how the player finds out their solution is broken
+// run with `cargo +nightly fuzz run process_step` from the repo root
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use conveyor_chaos::scene_level::block::{Block, BlockObject, BlockObjectMode};
+use conveyor_chaos::scene_level::board::BoardState;
+use conveyor_chaos::scene_level::helpers::*;
+use conveyor_chaos::scene_level::tile::TileType;
+
+// small enough that things actually run into each other
+const BOARD_SIZE: i32 = 12;
+const MAX_TICKS: i32 = 64;
+
+#[derive(Arbitrary, Debug)]
+struct FuzzLevel{
+    blockobjects: Vec<FuzzBlockObject>,
+    tiles: Vec<FuzzTile>,
+    push_levels: u8,
+    pushing_power: bool
+}
+
+#[derive(Arbitrary, Debug)]
+struct FuzzBlockObject{
+    input: bool,
+    cells: Vec<(u8, u8)>,
+    counter: u8,
+    x: i8,
+    y: i8,
+    quarter_turns: u8
+}
+
+#[derive(Arbitrary, Debug)]
+struct FuzzTile{
+    tiletype: u8,
+    x: i8,
+    y: i8,
+    dir: u8,
+    number: Option<i8>,
+    priority_level: Option<u8>
+}
+
+fn board_coord(n: i8) -> i32{
+    (n as i32).rem_euclid(BOARD_SIZE)
+}
+
+fn direction(n: u8) -> Direction{
+    match n % 4{
+        0 => Direction::Right,
+        1 => Direction::Down,
+        2 => Direction::Left,
+        _ => Direction::Up
+    }
+}
+
+fn build(level: &FuzzLevel) -> (SerializedSolution, Vec<BlockObject>, LevelRules){
+    let mut blockobjects = vec![];
+    let mut placements = vec![];
+    for (i, fbo) in level.blockobjects.iter().take(8).enumerate(){
+        let blocks: Vec<Block> = fbo.cells.iter()
+            .take(9)
+            .map(|(x, y)| Block::new(BoardPos{x: (*x % 4) as i32, y: (*y % 4) as i32}))
+            .collect();
+        if blocks.is_empty(){
+            continue;
+        }
+        let mode = if fbo.input {BlockObjectMode::Input} else {BlockObjectMode::Output};
+        let mut blockobject = BlockObject::from_blocklist(blocks, mode);
+        blockobject.id = i as i32 + 1;
+        blockobject.start_counter = (fbo.counter % 5) as i32 + 1;
+        blockobject.counter = blockobject.start_counter;
+        blockobjects.push(blockobject);
+        placements.push(SerializedPlacement{
+            id: i as i32 + 1,
+            pos: BoardPos{x: board_coord(fbo.x), y: board_coord(fbo.y)},
+            quarter_turns: fbo.quarter_turns as i32
+        });
+    }
+
+    let tiletypes: Vec<TileType> = TileType::all().collect();
+    let tiles = level.tiles.iter().take(64).map(|ftile| SerializedTile{
+        tiletype: tiletypes[ftile.tiletype as usize % tiletypes.len()],
+        pos: BoardPos{x: board_coord(ftile.x), y: board_coord(ftile.y)},
+        dir: Some(direction(ftile.dir)),
+        number: ftile.number.map(|n| n as i32),
+        priority_level: ftile.priority_level.map(|n| n as i32)
+    }).collect();
+
+    let priorities = serde_json::from_value(serde_json::json!({"push_levels": level.push_levels % 4 + 1}))
+        .expect("failed to build priority table");
+    let rules = LevelRules{
+        priorities,
+        push_resolution: if level.pushing_power {PushResolution::PushingPower} else {PushResolution::Strict}
+    };
+
+    let solution = SerializedSolution{
+        level: "fuzz".to_string(),
+        blockobjects: placements,
+        tiles
+    };
+    (solution, blockobjects, rules)
+}
+
+fuzz_target!(|level: FuzzLevel| {
+    let (solution, blockobjects, rules) = build(&level);
+    let mut state = BoardState::new();
+    state.set_rules(rules);
+    if state.load_solution(&solution, &blockobjects).is_err(){
+        return;
+    }
+    if state.process_start().is_err(){
+        return;
+    }
+    while state.get_game_ticks() < MAX_TICKS{
+        match state.process_step(){
+            Ok(false) => (),
+            Ok(true) | Err(_) => return
+        }
+    }
+});
@@ -0,0 +1,49 @@
+// runs the solution from each replay again with the current rules, and shows where it stopped matching
+// cargo run --bin replay_diff -- replays/Testlevel1-1234.json ...
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
+
+use ggez::GameResult;
+
+use conveyor_chaos::scene_level::replay::{Replay, ReplayOutcome};
+
+fn diff(path: &Path) -> GameResult<bool>{
+    let old = Replay::load(path)?;
+    // runs that finished get one extra tick, so we notice if the new one keeps going
+    let max_ticks = match old.outcome{
+        ReplayOutcome::Stopped => old.ticks.len(),
+        _ => old.ticks.len() + 1
+    };
+    let new = Replay::simulate(old.solution.clone(), max_ticks)?;
+    match old.diverge(&new)?{
+        Some(divergence) => {
+            println!("{}: {}", path.display(), divergence);
+            Ok(false)
+        },
+        None => {
+            println!("{}: same for all {} ticks", path.display(), old.ticks.len());
+            Ok(true)
+        }
+    }
+}
+
+fn main() -> ExitCode{
+    let paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty(){
+        eprintln!("usage: replay_diff <replay.json>...");
+        return ExitCode::FAILURE;
+    }
+
+    let mut all_same = true;
+    for path in paths.iter(){
+        match diff(Path::new(path)){
+            Ok(same) => all_same &= same,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                all_same = false;
+            }
+        }
+    }
+    if all_same {ExitCode::SUCCESS} else {ExitCode::FAILURE}
+}
@@ -0,0 +1,79 @@
+// draws a solution to a png without opening the game, for level thumbnails and bug reports
+// cargo run --bin snapshot -- tests/solutions/testlevel2_pinwheel_split.json [ticks] [out.png]
+// with ticks it runs that many ticks first, stopping early if it wins or errors
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use ggez::{GameResult, GameError};
+
+use conveyor_chaos::helpers::hidden_context;
+use conveyor_chaos::scene_level::board::{Board, BoardCanvas, BoardState};
+use conveyor_chaos::scene_level::constants::{SNAPSHOT_DIR, SNAPSHOT_SIZE};
+use conveyor_chaos::scene_level::export::timestamped_path;
+use conveyor_chaos::scene_level::helpers::SerializedSolution;
+use conveyor_chaos::scene_level::level::{LevelMode, LevelState};
+
+fn snapshot(solution_path: &str, ticks: i32, out: Option<PathBuf>) -> GameResult<PathBuf>{
+    let contents = fs::read_to_string(solution_path)?;
+    let solution: SerializedSolution = serde_json::from_str(&contents)
+        .map_err(|e| GameError::ResourceLoadError(format!("Failed to parse solution: {}", e)))?;
+    let (blockobjects, rules) = LevelState::load_level(&solution.level)?;
+    let mut state = BoardState::new();
+    state.set_rules(rules);
+    state.load_solution(&solution, &blockobjects)?;
+
+    let mut mode = LevelMode::Building;
+    if ticks > 0{
+        state.process_start()?;
+        mode = LevelMode::Running;
+        while state.get_game_ticks() < ticks{
+            match state.process_step(){
+                Ok(false) => (),
+                Ok(true) => {
+                    mode = LevelMode::Victory;
+                    break;
+                },
+                Err(sim_err) => {
+                    state.set_error(&sim_err);
+                    mode = LevelMode::Error;
+                    break;
+                }
+            }
+        }
+    }
+
+    let path = match out{
+        Some(path) => path,
+        None => timestamped_path(SNAPSHOT_DIR, &solution.level)?.with_extension("png")
+    };
+    let view = BoardCanvas::fit(&state.get_positions(), SNAPSHOT_SIZE);
+    let (mut ctx, _event_loop) = hidden_context()?;
+    // blocks that are mid animation are drawn where they ended up
+    Board::render_png(&mut ctx, &mut state, &view, &mode, 1.0, &path)?;
+    Ok(path)
+}
+
+fn main() -> ExitCode{
+    let args: Vec<String> = env::args().skip(1).collect();
+    let ticks = match args.get(1).map(|ticks| ticks.parse::<i32>()){
+        None => Ok(0),
+        Some(ticks) => ticks
+    };
+    let (Some(solution_path), Ok(ticks)) = (args.first(), ticks) else{
+        eprintln!("usage: snapshot <solution.json> [ticks] [out.png]");
+        return ExitCode::FAILURE;
+    };
+
+    match snapshot(solution_path, ticks, args.get(2).map(PathBuf::from)){
+        Ok(path) => {
+            println!("saved to {}", path.display());
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("{}: {}", solution_path, e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -3,10 +3,21 @@ use std::time::Duration;
 use ggez::graphics::Color;
 
 // window and other setup
+pub const GAME_ID: &str = "conveyor-chaos";
+pub const GAME_AUTHOR: &str = "r0ckwav3";
 pub const SCREEN_SIZE: (f32, f32) = (1920.0,1280.0);
 
 // UX
 pub const CLICK_TIME_THRESHOLD: Duration = Duration::from_millis(250);
 
+// files in the user's config directory
+pub const KEYMAP_FILE: &str = "keymap.json";
+pub const PREFERENCES_FILE: &str = "preferences.json";
+
+// the choices on the settings screen
+pub const RESOLUTIONS: [(f32, f32); 6] = [(1280.0,720.0), (1600.0,900.0), (1920.0,1080.0), (1920.0,1280.0), (2560.0,1440.0), (3840.0,2160.0)];
+pub const SIMULATION_SPEEDS: [f32; 5] = [0.5, 1.0, 2.0, 4.0, 8.0];
+pub const UI_SCALES: [f32; 6] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0];
+
 // colors
 pub const TRANSPARENT_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.0);
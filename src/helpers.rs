@@ -1,16 +1,35 @@
+use std::panic;
+
 use ggez::{
     GameResult,
     GameError,
     Context,
+    ContextBuilder,
+    conf::WindowMode,
+    event::EventLoop,
     graphics::{self,DrawParam,Transform,Image,Color},
     glam::{Mat2, vec2},
+    input::keyboard::KeyCode,
     mint::Point2
 };
 
+use crate::constants::*;
+
 // messages sent to mainstate from an individual scene
 pub enum SceneMessage{
     EnterSceneLevel{levelname: String},
     EnterSceneMainMenu,
+    EnterSceneSettings,
+}
+
+// a context whose window never shows up, for drawing things straight to files
+// winit panics instead of erroring when there's no display (or when this isn't the main thread), so that becomes an error too
+pub fn hidden_context() -> GameResult<(Context, EventLoop<()>)>{
+    panic::catch_unwind(|| {
+        ContextBuilder::new(GAME_ID, GAME_AUTHOR)
+            .window_mode(WindowMode::default().visible(false))
+            .build()
+    }).unwrap_or_else(|_| Err(GameError::CustomError("Couldn't open a window to draw with".to_string())))
 }
 
 // takes in a DrawParam and adjusts the dest so that that the original dest point is now the actual top left corner
@@ -80,3 +99,39 @@ pub fn weighted_color_ave(color1: Color, color2: Color, proportion: f32) -> Game
     }
 
 }
+
+// n for the Fn key, None for every other key
+pub fn function_key_value(keycode: KeyCode) -> Option<i32>{
+    match keycode{
+        KeyCode::F1 => Some(1),
+        KeyCode::F2 => Some(2),
+        KeyCode::F3 => Some(3),
+        KeyCode::F4 => Some(4),
+        KeyCode::F5 => Some(5),
+        KeyCode::F6 => Some(6),
+        KeyCode::F7 => Some(7),
+        KeyCode::F8 => Some(8),
+        KeyCode::F9 => Some(9),
+        KeyCode::F10 => Some(10),
+        KeyCode::F11 => Some(11),
+        KeyCode::F12 => Some(12),
+        _ => None
+    }
+}
+
+// the digit on a number key (top row or numpad), None for every other key
+pub fn number_key_value(keycode: KeyCode) -> Option<i32>{
+    match keycode{
+        KeyCode::Key0 | KeyCode::Numpad0 => Some(0),
+        KeyCode::Key1 | KeyCode::Numpad1 => Some(1),
+        KeyCode::Key2 | KeyCode::Numpad2 => Some(2),
+        KeyCode::Key3 | KeyCode::Numpad3 => Some(3),
+        KeyCode::Key4 | KeyCode::Numpad4 => Some(4),
+        KeyCode::Key5 | KeyCode::Numpad5 => Some(5),
+        KeyCode::Key6 | KeyCode::Numpad6 => Some(6),
+        KeyCode::Key7 | KeyCode::Numpad7 => Some(7),
+        KeyCode::Key8 | KeyCode::Numpad8 => Some(8),
+        KeyCode::Key9 | KeyCode::Numpad9 => Some(9),
+        _ => None
+    }
+}
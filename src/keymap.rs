@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::path::Path;
+use std::fs;
+
+use ggez::{
+    input::keyboard::{KeyCode, KeyInput, KeyMods},
+    GameResult, GameError
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+use crate::helpers::{number_key_value, function_key_value};
+
+// the keymap everything reads from, the settings screen swaps it out
+static KEYMAP: Lazy<Mutex<Keymap>> = Lazy::new(|| {
+    Mutex::new(Keymap::default())
+});
+
+// everything a key can do
+// number keys (tile numbers and the sidebar) aren't in here since they're more like typing than bindings
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Action{
+    Quit,
+    Confirm,
+    OpenSettings,
+    RotateCw,
+    RotateCcw,
+    FlipHorizontal,
+    FlipVertical,
+    RemoveTile,
+    Copy,
+    Cut,
+    Paste,
+    Duplicate,
+    MoveSelection,
+    DeleteSelection,
+    ZoomIn,
+    ZoomOut,
+    FitAll,
+    GoToOrigin,
+    ExportGif,
+    ExportFrames,
+    Snapshot
+}
+
+// a key along with the modifiers that have to be held for it
+// alt and the logo key are ignored
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct Binding{
+    pub key: KeyCode,
+    #[serde(default)]
+    pub ctrl: bool,
+    #[serde(default)]
+    pub shift: bool
+}
+
+// why a key couldn't be bound
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BindError{
+    Taken(Action), // the other action that uses it
+    Reserved
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Keymap{
+    bindings: HashMap<Action, Vec<Binding>>
+}
+
+impl Action{
+    // in the order the settings screen lists them
+    pub const ALL: [Action; 21] = [
+        Action::Quit,
+        Action::Confirm,
+        Action::OpenSettings,
+        Action::RotateCw,
+        Action::RotateCcw,
+        Action::FlipHorizontal,
+        Action::FlipVertical,
+        Action::RemoveTile,
+        Action::Copy,
+        Action::Cut,
+        Action::Paste,
+        Action::Duplicate,
+        Action::MoveSelection,
+        Action::DeleteSelection,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::FitAll,
+        Action::GoToOrigin,
+        Action::ExportGif,
+        Action::ExportFrames,
+        Action::Snapshot
+    ];
+
+    pub fn name(&self) -> &'static str{
+        match self{
+            Action::Quit => "Quit",
+            Action::Confirm => "Start/stop run, close popups",
+            Action::OpenSettings => "Open settings",
+            Action::RotateCw => "Rotate clockwise",
+            Action::RotateCcw => "Rotate counterclockwise",
+            Action::FlipHorizontal => "Flip left to right",
+            Action::FlipVertical => "Flip upside down",
+            Action::RemoveTile => "Remove tile",
+            Action::Copy => "Copy selection",
+            Action::Cut => "Cut selection",
+            Action::Paste => "Paste",
+            Action::Duplicate => "Duplicate selection",
+            Action::MoveSelection => "Move selection",
+            Action::DeleteSelection => "Delete selection",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::FitAll => "Fit everything",
+            Action::GoToOrigin => "Go to origin",
+            Action::ExportGif => "Export run as gif",
+            Action::ExportFrames => "Export run as frames",
+            Action::Snapshot => "Snapshot the board"
+        }
+    }
+
+    fn default_bindings(&self) -> Vec<Binding>{
+        let key = |key| Binding{key, ctrl: false, shift: false};
+        let ctrl = |key| Binding{key, ctrl: true, shift: false};
+        let shift = |key| Binding{key, ctrl: false, shift: true};
+        match self{
+            Action::Quit => vec![key(KeyCode::Escape)],
+            Action::Confirm => vec![key(KeyCode::Return)],
+            Action::OpenSettings => vec![key(KeyCode::S)],
+            Action::RotateCw => vec![key(KeyCode::R)],
+            Action::RotateCcw => vec![shift(KeyCode::R)],
+            Action::FlipHorizontal => vec![key(KeyCode::X)],
+            Action::FlipVertical => vec![shift(KeyCode::X)],
+            Action::RemoveTile => vec![key(KeyCode::D)],
+            Action::Copy => vec![ctrl(KeyCode::C)],
+            Action::Cut => vec![ctrl(KeyCode::X)],
+            Action::Paste => vec![ctrl(KeyCode::V)],
+            Action::Duplicate => vec![ctrl(KeyCode::D)],
+            Action::MoveSelection => vec![key(KeyCode::M)],
+            Action::DeleteSelection => vec![key(KeyCode::Delete), key(KeyCode::Back)],
+            // + is shift+= on most keyboards
+            Action::ZoomIn => vec![key(KeyCode::Equals), shift(KeyCode::Equals), key(KeyCode::Plus), key(KeyCode::NumpadAdd)],
+            Action::ZoomOut => vec![key(KeyCode::Minus), key(KeyCode::NumpadSubtract)],
+            Action::FitAll => vec![key(KeyCode::F)],
+            Action::GoToOrigin => vec![key(KeyCode::H), key(KeyCode::Home)],
+            Action::ExportGif => vec![key(KeyCode::G)],
+            Action::ExportFrames => vec![shift(KeyCode::G)],
+            Action::Snapshot => vec![key(KeyCode::F12)]
+        }
+    }
+
+    // reserved keys only go to the actions that have them by default, like escape for quitting
+    fn can_bind(&self, binding: Binding) -> bool{
+        !binding.is_reserved() || self.default_bindings().contains(&binding)
+    }
+}
+
+impl Binding{
+    // None for keys that can't be bound by themselves, like shift
+    pub fn from_input(input: &KeyInput) -> Option<Binding>{
+        let key = input.keycode?;
+        if matches!(key,
+            KeyCode::LShift | KeyCode::RShift | KeyCode::LControl | KeyCode::RControl |
+            KeyCode::LAlt | KeyCode::RAlt | KeyCode::LWin | KeyCode::RWin
+        ){
+            return None;
+        }
+        Some(Binding{
+            key,
+            ctrl: input.mods.contains(KeyMods::CTRL),
+            shift: input.mods.contains(KeyMods::SHIFT)
+        })
+    }
+
+    // number keys and F1-F11 pick tile numbers and sidebar items, and the settings screen gets around with the rest
+    // with or without modifiers, since those are read from the key alone
+    pub fn is_reserved(&self) -> bool{
+        number_key_value(self.key).is_some()
+            || function_key_value(self.key).is_some_and(|n| n <= 11)
+            || matches!(self.key,
+                KeyCode::Up | KeyCode::Down | KeyCode::Tab | KeyCode::Return |
+                KeyCode::Delete | KeyCode::Back | KeyCode::Escape
+            )
+    }
+
+    pub fn label(&self) -> String{
+        let key = match self.key{
+            KeyCode::Return => "Enter".to_string(),
+            KeyCode::Back => "Backspace".to_string(),
+            KeyCode::Escape => "Esc".to_string(),
+            other => format!("{:?}", other)
+        };
+        match (self.ctrl, self.shift){
+            (false, false) => key,
+            (true, false) => format!("Ctrl+{}", key),
+            (false, true) => format!("Shift+{}", key),
+            (true, true) => format!("Ctrl+Shift+{}", key)
+        }
+    }
+}
+
+impl Default for Keymap{
+    fn default() -> Keymap{
+        Keymap{
+            bindings: Action::ALL.iter().map(|action| (*action, action.default_bindings())).collect()
+        }
+    }
+}
+
+impl Keymap{
+    // actions missing from the file keep their defaults, so old files still work when actions get added
+    pub fn from_json(json: &str) -> GameResult<Keymap>{
+        let loaded: Keymap = serde_json::from_str(json)
+            .map_err(|e| GameError::ConfigError(format!("Failed to read keymap: {}", e)))?;
+        let mut keymap = Keymap::default();
+        keymap.bindings.extend(loaded.bindings);
+        for action in Action::ALL{
+            if let Some(binding) = keymap.bindings(action).iter().find(|binding| !action.can_bind(**binding)){
+                return Err(GameError::ConfigError(format!(
+                    "{} can't be bound to {}, that key is reserved", binding.label(), action.name()
+                )));
+            }
+        }
+        if let Some((binding, first, second)) = keymap.conflicts().first(){
+            return Err(GameError::ConfigError(format!(
+                "{} is bound to both {} and {}", binding.label(), first.name(), second.name()
+            )));
+        }
+        Ok(keymap)
+    }
+
+    // the defaults if there's no keymap file yet
+    pub fn load(config_dir: &Path) -> GameResult<Keymap>{
+        let path = config_dir.join(KEYMAP_FILE);
+        if !path.exists(){
+            return Ok(Keymap::default());
+        }
+        Keymap::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, config_dir: &Path) -> GameResult{
+        fs::create_dir_all(config_dir)?;
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| GameError::ConfigError(format!("Failed to write keymap: {}", e)))?;
+        fs::write(config_dir.join(KEYMAP_FILE), contents)?;
+        Ok(())
+    }
+
+    pub fn action(&self, binding: Binding) -> Option<Action>{
+        Action::ALL.iter().copied().find(|action| self.bindings(*action).contains(&binding))
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding]{
+        self.bindings.get(&action).map(|b| b.as_slice()).unwrap_or(&[])
+    }
+
+    // replaces whatever the action was bound to
+    // fails with the other action if the binding is already taken
+    pub fn bind(&mut self, action: Action, binding: Binding) -> Result<(), BindError>{
+        if !action.can_bind(binding){
+            return Err(BindError::Reserved);
+        }
+        match self.action(binding){
+            Some(other) if other != action => Err(BindError::Taken(other)),
+            _ => {
+                self.bindings.insert(action, vec![binding]);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn reset(&mut self, action: Action) -> Result<(), Action>{
+        for binding in action.default_bindings(){
+            match self.action(binding){
+                Some(other) if other != action => return Err(other),
+                _ => ()
+            }
+        }
+        self.bindings.insert(action, action.default_bindings());
+        Ok(())
+    }
+
+    // every binding that more than one action uses
+    pub fn conflicts(&self) -> Vec<(Binding, Action, Action)>{
+        let mut seen: HashMap<Binding, Action> = HashMap::new();
+        let mut conflicts = Vec::new();
+        for action in Action::ALL{
+            for binding in self.bindings(action){
+                match seen.get(binding){
+                    Some(other) if *other != action => conflicts.push((*binding, *other, action)),
+                    _ => {
+                        seen.insert(*binding, action);
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+// what a key press does with the current keymap
+pub fn action(input: &KeyInput) -> Option<Action>{
+    let binding = Binding::from_input(input)?;
+    KEYMAP.lock().unwrap().action(binding)
+}
+
+pub fn current() -> Keymap{
+    KEYMAP.lock().unwrap().clone()
+}
+
+pub fn set(keymap: Keymap){
+    *KEYMAP.lock().unwrap() = keymap;
+}
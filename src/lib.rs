@@ -0,0 +1,11 @@
+// everything lives in the library so the simulation can be tested without opening a window
+pub mod scene_level;
+pub mod scene_main_menu;
+pub mod scene_settings;
+
+pub mod constants;
+pub mod helpers;
+pub mod mainstate;
+pub mod asset_cache;
+pub mod keymap;
+pub mod preferences;
@@ -1,19 +1,21 @@
-use ggez::{event, GameResult};
+use ggez::{event, filesystem::Filesystem, GameResult};
 
-use constants::*;
-
-mod scene_level;
-mod scene_main_menu;
-
-pub mod constants;
-pub mod helpers;
-pub mod mainstate;
-pub mod asset_cache;
+use conveyor_chaos::constants::*;
+use conveyor_chaos::mainstate;
+use conveyor_chaos::preferences::{self, Preferences};
 
 pub fn main() -> GameResult {
-    let cb = ggez::ContextBuilder::new("conveyor-chaos", "r0ckwav3")
-        .window_setup(ggez::conf::WindowSetup::default().title("A Manufacturing Game(TM)"))
-        .window_mode(ggez::conf::WindowMode::default().dimensions(SCREEN_SIZE.0, SCREEN_SIZE.1));
+    // the window needs the preferences before there's a context to find the config directory with
+    let config_dir = Filesystem::new(GAME_ID, GAME_AUTHOR, "resources", "resources.zip")?.user_config_dir().to_path_buf();
+    let prefs = Preferences::load(&config_dir).unwrap_or_else(|e| {
+        eprintln!("{}, using the default preferences", e);
+        Preferences::default()
+    });
+    preferences::set(prefs);
+
+    let cb = ggez::ContextBuilder::new(GAME_ID, GAME_AUTHOR)
+        .window_setup(ggez::conf::WindowSetup::default().title("A Manufacturing Game(TM)").vsync(prefs.vsync))
+        .window_mode(prefs.window_mode());
     let (mut ctx, event_loop) = cb.build()?;
     let state = mainstate::MainState::new(&mut ctx)?;
     event::run(ctx, event_loop, state)
@@ -3,12 +3,15 @@ use std::sync::mpsc;
 
 use ggez::{
     event,
-    input::{mouse::MouseButton, keyboard::KeyInput, keyboard::KeyCode},
+    input::{mouse::MouseButton, keyboard::KeyInput},
     Context, GameResult,
 };
 
 use crate::scene_level::level::LevelState;
 use crate::scene_main_menu::main_menu::MainMenuState;
+use crate::scene_settings::settings::SettingsState;
+use crate::keymap::{self, Keymap, Action};
+use crate::preferences;
 use crate::constants::*;
 use crate::helpers::*;
 
@@ -20,6 +23,12 @@ pub struct MainState {
 
 impl MainState {
     pub fn new(ctx: &mut Context) -> GameResult<MainState> {
+        // a broken keymap file shouldn't stop the game from starting
+        match Keymap::load(ctx.fs.user_config_dir()){
+            Ok(keymap) => keymap::set(keymap),
+            Err(e) => eprintln!("{}, using the default keys", e)
+        }
+
         let (s,r) = mpsc::channel();
         Ok(MainState{
             scene_channel_r: r,
@@ -43,6 +52,12 @@ impl MainState {
                     self.scene = Box::new(MainMenuState::new(ctx, s)?);
                     self.scene_channel_r = r;
                 }
+                SceneMessage::EnterSceneSettings => {
+                    self.scene.cleanup(ctx)?;
+                    let (s,r) = mpsc::channel();
+                    self.scene = Box::new(SettingsState::new(ctx, s)?);
+                    self.scene_channel_r = r;
+                }
             }
         }
         Ok(())
@@ -60,7 +75,10 @@ impl event::EventHandler for MainState {
         self.scene.draw(ctx)
     }
 
+    // scenes get the mouse in ui units, see preferences::screen_rect
     fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> GameResult{
+        let scale = preferences::current().ui_scale;
+        let (x, y) = (x / scale, y / scale);
         if button == MouseButton::Left{
             self.click_time = ctx.time.time_since_start();
         }
@@ -69,6 +87,8 @@ impl event::EventHandler for MainState {
     }
 
     fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> GameResult{
+        let scale = preferences::current().ui_scale;
+        let (x, y) = (x / scale, y / scale);
         if button == MouseButton::Left{
             let time_since_click = ctx.time.time_since_start() - self.click_time;
             if time_since_click < CLICK_TIME_THRESHOLD{
@@ -80,11 +100,20 @@ impl event::EventHandler for MainState {
     }
 
     fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, dx: f32, dy: f32) -> GameResult{
-        self.scene.mouse_motion_event(ctx,x,y,dx,dy)
+        let scale = preferences::current().ui_scale;
+        self.scene.mouse_motion_event(ctx, x / scale, y / scale, dx / scale, dy / scale)
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) -> GameResult{
+        self.scene.mouse_wheel_event(ctx, x, y)
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) -> GameResult{
+        self.scene.resize_event(ctx, width, height)
     }
 
     fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, repeated: bool) -> GameResult {
-        if input.keycode == Some(KeyCode::Escape) {
+        if !self.scene.captures_keys() && keymap::action(&input) == Some(Action::Quit){
             ctx.request_quit();
         }
         self.scene.key_down_event(ctx, input, repeated)?;
@@ -106,4 +135,9 @@ pub trait SceneState: event::EventHandler{
     fn cleanup(&mut self, ctx: &mut Context) -> GameResult {
         Ok(())
     }
+
+    // scenes that read raw keys (like when rebinding them) stop the quit key from working
+    fn captures_keys(&self) -> bool{
+        false
+    }
 }
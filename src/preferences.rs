@@ -0,0 +1,154 @@
+use std::sync::Mutex;
+use std::path::Path;
+use std::fs;
+
+use ggez::{
+    conf::{WindowMode, FullscreenType},
+    graphics::{Color, Rect},
+    mint::Point2,
+    Context, GameResult, GameError
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+
+// the preferences everything reads from, loaded in main before the window opens
+static PREFERENCES: Lazy<Mutex<Preferences>> = Lazy::new(|| {
+    Mutex::new(Preferences::default())
+});
+
+// anything missing from the file gets its default
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Preferences{
+    pub resolution: (f32, f32), // the window size it starts at when it isn't fullscreen
+    pub fullscreen: bool,
+    pub vsync: bool, // only changes when the game starts
+    pub simulation_speed: f32, // how many ticks a second a run starts at, relative to the normal speed
+    pub ui_scale: f32,
+    pub theme: Theme
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme{
+    Workshop,
+    Night,
+    Blueprint
+}
+
+// the colors that change with the theme
+pub struct ThemeColors{
+    pub board_bg: Color,
+    pub sidebar_bg: Color,
+    pub popup_bg: Color
+}
+
+impl Default for Preferences{
+    fn default() -> Preferences{
+        Preferences{
+            resolution: SCREEN_SIZE,
+            fullscreen: false,
+            vsync: true,
+            simulation_speed: 1.0,
+            ui_scale: 1.0,
+            theme: Theme::Workshop
+        }
+    }
+}
+
+impl Preferences{
+    pub fn from_json(json: &str) -> GameResult<Preferences>{
+        let preferences: Preferences = serde_json::from_str(json)
+            .map_err(|e| GameError::ConfigError(format!("Failed to read preferences: {}", e)))?;
+        // hand edited files could have anything in them
+        if preferences.simulation_speed <= 0.0 || preferences.ui_scale <= 0.0
+            || preferences.resolution.0 <= 0.0 || preferences.resolution.1 <= 0.0{
+            return Err(GameError::ConfigError("Preferences have a size or speed that isn't positive".to_string()));
+        }
+        Ok(preferences)
+    }
+
+    // the defaults if there's no preferences file yet
+    pub fn load(config_dir: &Path) -> GameResult<Preferences>{
+        let path = config_dir.join(PREFERENCES_FILE);
+        if !path.exists(){
+            return Ok(Preferences::default());
+        }
+        Preferences::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, config_dir: &Path) -> GameResult{
+        fs::create_dir_all(config_dir)?;
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| GameError::ConfigError(format!("Failed to write preferences: {}", e)))?;
+        fs::write(config_dir.join(PREFERENCES_FILE), contents)?;
+        Ok(())
+    }
+
+    pub fn window_mode(&self) -> WindowMode{
+        let fullscreen_type = if self.fullscreen {FullscreenType::Desktop} else {FullscreenType::Windowed};
+        WindowMode::default()
+            .dimensions(self.resolution.0, self.resolution.1)
+            .fullscreen_type(fullscreen_type)
+            .resizable(true)
+    }
+}
+
+impl Theme{
+    pub const ALL: [Theme; 3] = [Theme::Workshop, Theme::Night, Theme::Blueprint];
+
+    pub fn name(&self) -> &'static str{
+        match self{
+            Theme::Workshop => "Workshop",
+            Theme::Night => "Night",
+            Theme::Blueprint => "Blueprint"
+        }
+    }
+
+    pub fn colors(&self) -> ThemeColors{
+        match self{
+            Theme::Workshop => ThemeColors{
+                board_bg: Color::new(106.0/255.0, 86.0/255.0, 73.0/255.0, 1.0),
+                sidebar_bg: Color::new(0.5, 0.5, 0.5, 1.0),
+                popup_bg: Color::new(0.7, 0.7, 0.7, 1.0)
+            },
+            Theme::Night => ThemeColors{
+                board_bg: Color::new(0.16, 0.17, 0.2, 1.0),
+                sidebar_bg: Color::new(0.22, 0.23, 0.27, 1.0),
+                popup_bg: Color::new(0.32, 0.34, 0.4, 1.0)
+            },
+            Theme::Blueprint => ThemeColors{
+                board_bg: Color::new(0.12, 0.25, 0.45, 1.0),
+                sidebar_bg: Color::new(0.2, 0.33, 0.52, 1.0),
+                popup_bg: Color::new(0.35, 0.5, 0.7, 1.0)
+            }
+        }
+    }
+}
+
+pub fn current() -> Preferences{
+    *PREFERENCES.lock().unwrap()
+}
+
+pub fn set(preferences: Preferences){
+    *PREFERENCES.lock().unwrap() = preferences;
+}
+
+pub fn theme() -> ThemeColors{
+    current().theme.colors()
+}
+
+// everything is laid out in ui units, which are pixels divided by the ui scale
+pub fn screen_rect(ctx: &Context) -> Rect{
+    let (w, h) = ctx.gfx.drawable_size();
+    let scale = current().ui_scale;
+    Rect::new(0.0, 0.0, w / scale, h / scale)
+}
+
+pub fn mouse_position(ctx: &Context) -> Point2<f32>{
+    let pos = ctx.mouse.position();
+    let scale = current().ui_scale;
+    Point2{x: pos.x / scale, y: pos.y / scale}
+}
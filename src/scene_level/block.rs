@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::f32::consts::PI;
 
 use ggez::{
     glam,
@@ -28,7 +30,9 @@ pub struct BlockObject{
     pub start_counter: i32,
     pub counter: i32,
     pub just_moved: bool,
-    image_cache: Option<graphics::Image>,
+    pub last_move: MovementType, // what the simulation did with it last tick, anim is only for drawing
+    pub gate_arrivals: HashMap<BoardPos, bool>, // gates this has tried to get through, and whether it was let through
+    image_cache: Option<(f32, graphics::Image)>, // along with the tile size it was drawn at
     top_left: Option<BoardPos>,
     bottom_right: Option<BoardPos>
 }
@@ -44,6 +48,8 @@ pub enum BlockObjectMode{
 pub enum BlockObjectAnimation{
     Translation{x: f32, y:f32},
     Rotation{theta: f32, around: BoardPos},
+    Mirror{vertical: bool, around: BoardPos},
+    Teleport{x: f32, y: f32},
     Output
 }
 
@@ -59,7 +65,9 @@ impl BlockObject{
             anim: BlockObjectAnimation::Translation{x:0.0, y:0.0},
             start_counter: 0,
             counter: 0,
-            just_moved: true
+            just_moved: true,
+            last_move: MovementType::None,
+            gate_arrivals: HashMap::new()
         }
     }
 
@@ -74,7 +82,9 @@ impl BlockObject{
             anim: BlockObjectAnimation::Translation{x:0.0, y:0.0},
             counter: 0,
             start_counter: 0,
-            just_moved: true
+            just_moved: true,
+            last_move: MovementType::None,
+            gate_arrivals: HashMap::new()
         }
     }
 
@@ -92,12 +102,14 @@ impl BlockObject{
     }
 
     pub fn draw(&mut self, ctx: &mut Context, tilesize: f32) -> GameResult<graphics::Image>{
-        if let Some(image) = self.image_cache.clone(){
-            Ok(image)
-        }else{
-            self.generate_image(ctx, tilesize)?;
-            let image = self.image_cache.clone().expect("Failed to cache image");
-            Ok(image)
+        match self.image_cache.clone(){
+            // the board can be zoomed, so an image at the old size is no good
+            Some((cached_size, image)) if cached_size == tilesize => Ok(image),
+            _ => {
+                self.generate_image(ctx, tilesize)?;
+                let (_, image) = self.image_cache.clone().expect("Failed to cache image");
+                Ok(image)
+            }
         }
     }
 
@@ -212,7 +224,7 @@ impl BlockObject{
 
         image_canvas.finish(ctx)?;
 
-        self.image_cache = Some(image);
+        self.image_cache = Some((tilesize, image));
         Ok(())
     }
 
@@ -318,86 +330,119 @@ impl BlockObject{
         self.reset_cache();
     }
 
-    pub fn block_locations(&self) -> Vec<BoardPos>{
-        let mut ans = Vec::new();
-        for block in self.blocks.iter(){
-            ans.push(block.pos)
+    // vertical mirrors across the vertical line through around, otherwise the horizontal line
+    pub fn mirror(&mut self, vertical: bool, around: BoardPos){
+        for block in self.blocks.iter_mut(){
+            block.mirror(vertical, around);
         }
-        ans
+        self.reset_cache();
     }
 
-    // return all places where there is a block at x and x+1
-    pub fn get_vert_seam(&self, x: i32) -> Vec<i32>{
-        let mut seam_map: HashMap<i32, (bool, bool)> = HashMap::new();
+    // every position a block passes over while mirroring, including where it starts and ends
+    pub fn mirror_sweep(&self, vertical: bool, around: BoardPos) -> Vec<BoardPos>{
+        let mut swept = HashSet::new();
         for block in self.blocks.iter(){
-            if block.pos.x == x{
-                seam_map.insert(
-                    block.pos.y,
-                    seam_map.get(&block.pos.y).map_or((true, false), |p| (true, p.1))
-                );
-            }
-            if block.pos.x == x+1{
-                seam_map.insert(
-                    block.pos.y,
-                    seam_map.get(&block.pos.y).map_or((false, true), |p| (p.0, true))
-                );
+            let mut end = *block;
+            end.mirror(vertical, around);
+            if vertical{
+                for x in block.pos.x.min(end.pos.x)..=block.pos.x.max(end.pos.x){
+                    swept.insert(BoardPos{x, y: block.pos.y});
+                }
+            }else{
+                for y in block.pos.y.min(end.pos.y)..=block.pos.y.max(end.pos.y){
+                    swept.insert(BoardPos{x: block.pos.x, y});
+                }
             }
         }
+        swept.into_iter().collect()
+    }
 
-        let mut ans: Vec<i32> = Vec::new();
-        for (k, v) in seam_map{
-            if v == (true, true){
-                ans.push(k);
+    // positive quarter_turns are clockwise
+    pub fn rotate(&mut self, quarter_turns: i32, around: BoardPos){
+        for _ in 0..quarter_turns.abs(){
+            if quarter_turns > 0{
+                self.rotate_cw(around);
+            }else{
+                self.rotate_ccw(around);
             }
         }
-        ans
     }
 
-    pub fn get_hori_seam(&self, y: i32) -> Vec<i32>{
-        let mut seam_map: HashMap<i32, (bool, bool)> = HashMap::new();
+    // every position a block passes over while rotating, including where it starts and ends
+    // we follow a few points inside each block along the arc, so a block only clipping the
+    // very corner of a position does not count as passing over it
+    pub fn rotation_sweep(&self, quarter_turns: i32, around: BoardPos) -> Vec<BoardPos>{
+        let mut swept = HashSet::new();
+        let total_angle = quarter_turns as f32 * PI / 2.0;
+        let samples = [-SWEEP_SAMPLE_INSET, 0.0, SWEEP_SAMPLE_INSET];
         for block in self.blocks.iter(){
-            if block.pos.y == y{
-                let mut curr = *seam_map.get(&block.pos.x).unwrap_or(&(false, false));
-                curr.0 = true;
-                seam_map.insert(block.pos.x, curr);
-            }
-            if block.pos.y == y+1{
-                let mut curr = *seam_map.get(&block.pos.x).unwrap_or(&(false, false));
-                curr.1 = true;
-                seam_map.insert(block.pos.x, curr);
+            let rel = glam::vec2((block.pos.x - around.x) as f32, (block.pos.y - around.y) as f32);
+            // the outermost point travels the furthest
+            let radius = rel.length() + SWEEP_SAMPLE_INSET * 2.0_f32.sqrt();
+            let steps = ((radius * total_angle.abs()) / SWEEP_SAMPLE_SPACING).ceil().max(1.0) as i32;
+            for step in 0..=steps{
+                // with y pointing down, a positive angle is clockwise
+                let rot_mat = glam::Mat2::from_angle(total_angle * step as f32 / steps as f32);
+                for dx in samples{
+                    for dy in samples{
+                        let point = rot_mat * (rel + glam::vec2(dx, dy));
+                        swept.insert(BoardPos{
+                            x: around.x + point.x.round() as i32,
+                            y: around.y + point.y.round() as i32
+                        });
+                    }
+                }
             }
         }
+        swept.into_iter().collect()
+    }
 
-        let mut ans: Vec<i32> = Vec::new();
-        for (k, v) in seam_map{
-            if v == (true, true){
-                ans.push(k);
-            }
+    pub fn block_locations(&self) -> Vec<BoardPos>{
+        let mut ans = Vec::new();
+        for block in self.blocks.iter(){
+            ans.push(block.pos)
         }
         ans
     }
 
-    // transforms into left part, returns right
-    pub fn split_vert_seam(&mut self, x: i32) -> Self{
-        let mut other_blocks = vec![];
-        let mut i = 0;
-        while i < self.blocks.len(){
-            if self.blocks[i].pos.x > x{
-                other_blocks.push(self.blocks.remove(i));
-            }else{
-                i += 1
+    // the connected pieces left after cutting between x and x+1 for every x in vert_seams,
+    // and between y and y+1 for every y in hori_seams
+    pub fn seam_pieces(&self, vert_seams: &HashSet<i32>, hori_seams: &HashSet<i32>) -> Vec<Vec<BoardPos>>{
+        let locations: HashSet<BoardPos> = self.block_locations().into_iter().collect();
+        let mut seen: HashSet<BoardPos> = HashSet::new();
+        let mut pieces = vec![];
+        for start in self.block_locations(){
+            if !seen.insert(start){
+                continue;
+            }
+            // flood fill without crossing any seams
+            let mut piece = vec![];
+            let mut stack = vec![start];
+            while let Some(pos) = stack.pop(){
+                piece.push(pos);
+                let neighbours = [
+                    (BoardPos{x: pos.x+1, y: pos.y}, !vert_seams.contains(&pos.x)),
+                    (BoardPos{x: pos.x-1, y: pos.y}, !vert_seams.contains(&(pos.x-1))),
+                    (BoardPos{x: pos.x, y: pos.y+1}, !hori_seams.contains(&pos.y)),
+                    (BoardPos{x: pos.x, y: pos.y-1}, !hori_seams.contains(&(pos.y-1)))
+                ];
+                for (next, connected) in neighbours{
+                    if connected && locations.contains(&next) && seen.insert(next){
+                        stack.push(next);
+                    }
+                }
             }
+            pieces.push(piece);
         }
-        self.reset_cache();
-        Self::from_blocklist(other_blocks, BlockObjectMode::Processing)
+        pieces
     }
 
-    // transforms into top part, returns bottom
-    pub fn split_hori_seam(&mut self, y: i32) -> Self{
+    // removes the blocks at positions, returns them as a new block object
+    pub fn split_off(&mut self, positions: &[BoardPos]) -> Self{
         let mut other_blocks = vec![];
         let mut i = 0;
         while i < self.blocks.len(){
-            if self.blocks[i].pos.y > y{
+            if positions.contains(&self.blocks[i].pos){
                 other_blocks.push(self.blocks.remove(i));
             }else{
                 i += 1
@@ -408,6 +453,12 @@ impl BlockObject{
     }
 }
 
+impl Default for BlockObject{
+    fn default() -> BlockObject{
+        BlockObject::new()
+    }
+}
+
 impl Clone for BlockObject{
     fn clone(&self) -> BlockObject{
         return BlockObject{
@@ -420,7 +471,9 @@ impl Clone for BlockObject{
             anim: self.anim.clone(),
             counter: self.counter,
             start_counter: self.start_counter,
-            just_moved: self.just_moved
+            just_moved: self.just_moved,
+            last_move: self.last_move,
+            gate_arrivals: self.gate_arrivals.clone()
         }
     }
 }
@@ -455,6 +508,14 @@ impl Block{
         }
     }
 
+    pub fn mirror(&mut self, vertical: bool, around: BoardPos){
+        if vertical{
+            self.pos.x = 2*around.x - self.pos.x;
+        }else{
+            self.pos.y = 2*around.y - self.pos.y;
+        }
+    }
+
     pub fn draw(ctx: &mut Context, tilesize: f32, nhood: [[bool; 3]; 3]) -> GameResult<graphics::Image>{
         let color_format = ctx.gfx.surface_format();
         let image = graphics::Image::new_canvas_image(
@@ -1,4 +1,5 @@
 use std::time::Duration;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::f32::consts::PI;
@@ -7,51 +8,118 @@ use ggez::{
     glam,
     graphics,
     input::mouse::MouseButton,
-    input::keyboard::{KeyInput, KeyCode, KeyMods},
-    Context, GameResult
+    input::keyboard::{KeyInput, KeyMods},
+    Context, GameResult, GameError
 };
 
 use super::level::{Holding, LevelMode};
 use super::tile::{Tile, TileType};
+use super::tile_registry::TileMovement;
 use super::block::{BlockObject, BlockObjectMode, BlockObjectAnimation};
+use super::replay::{ReplayEvent, sorted_blocks};
+use super::export::{Exporter, ExportFormat, rgba_image, timestamped_path, image_error};
 use super::helpers::*;
 use super::constants::*;
 
 use crate::helpers::*;
 use crate::asset_cache;
+use crate::keymap::{self, Action};
+use crate::preferences;
 
 pub struct Board {
-    mouse_down: bool,
+    mouse_down: Option<MouseButton>, // whichever button is panning the board
     canvas: BoardCanvas,
-    state: BoardState
+    state: BoardState,
+    selection: Option<Selection>,
+    clipboard: Vec<Tile>, // relative to the top left of what was copied
+    stroke: Option<Stroke>
 }
 
-struct BoardCanvas {
+// dragging across the board to paint the held tile onto every cell, or to erase them
+struct Stroke {
+    erase: bool,
+    last: BoardPos, // the cell the cursor was last in
+    painted: bool // once something is painted, letting go isn't a click anymore
+}
+
+// a rectangle of tiles picked out by ctrl+dragging
+#[derive(Clone, Copy)]
+struct Selection {
+    start: BoardPos, // where the drag started
+    end: BoardPos,
+    dragging: bool
+}
+
+// an export that's partway through, the level draws a few more frames of it every update
+pub struct RunExport {
+    exporter: Exporter,
+    state: BoardState,
+    view: BoardCanvas,
+    mode: LevelMode,
+    animation_proportion: f32,
+    total_ticks: i32, // how long the dry run went for, only used for showing progress
+    done: bool
+}
+
+// which part of the board to show, and how big
+#[derive(Clone)]
+pub struct BoardCanvas {
     pos: graphics::Rect, // where to render it on the screen
     tile_size: f32,
     offset_x: f32, // the top left corner of the screen should show what's at (offset_x, offset_y)
     offset_y: f32
 }
 
-struct BoardState {
+// where the minimap is on the screen, and which part of the board it shows
+struct Minimap {
+    rect: graphics::Rect,
+    left: f32, // the board position at the top left corner, in tiles
+    top: f32,
+    scale: f32 // pixels per tile
+}
+
+// the simulation side of the board, this doesn't need a Context so it can be run headless
+#[derive(Clone)]
+pub struct BoardState {
     animation_duration: Duration,
     animation_timer: Duration,
     game_ticks: i32,
     tiles: Vec<Tile>,
     blockobjects: Vec<BlockObject>,
     activeblockobjects: Vec<BlockObject>,
-    error_locs: Vec<BoardPos>
+    rules: LevelRules,
+    error_locs: Vec<BoardPos>,
+    error_blockobjects: Vec<usize>, // indices into activeblockobjects
+    split_diagnostics: Option<SplitDiagnostics>,
+    replay_ticks: Vec<Vec<ReplayEvent>> // what happened on each tick of the current run
 }
 
 impl Board{
     pub fn new(screenpos: graphics::Rect) -> Board {
         Board{
-            mouse_down: false,
+            mouse_down: None,
             canvas: BoardCanvas::new(screenpos),
-            state: BoardState::new()
+            state: BoardState::new(),
+            selection: None,
+            clipboard: Vec::new(),
+            stroke: None
         }
     }
 
+    pub fn set_rules(&mut self, rules: LevelRules){
+        self.state.set_rules(rules);
+    }
+
+    // when the window changes size
+    pub fn set_pos(&mut self, screenpos: graphics::Rect){
+        self.canvas.resize(screenpos);
+    }
+
+    // relative to the normal speed, 2.0 runs twice as many ticks a second
+    pub fn set_simulation_speed(&mut self, speed: f32){
+        self.state.animation_duration = Duration::from_secs_f32(ANIMATION_DURATION / speed);
+    }
+
     pub fn update(&mut self, ctx: &mut Context, mode: &LevelMode) -> SimulationResult<bool> {
         match mode{
             LevelMode::Running => {
@@ -61,7 +129,7 @@ impl Board{
                     match self.state.process_step(){
                         Ok(true) => {return Ok(true);},
                         Err(sim_err) => {
-                            self.state.error_locs = sim_err.relevant_locations.clone();
+                            self.state.set_error(&sim_err);
                             return Err(sim_err);
                         },
                         _ => ()
@@ -74,31 +142,161 @@ impl Board{
     }
 
     pub fn draw(&mut self, ctx: &mut Context, out_canvas: &mut graphics::Canvas, mode: &LevelMode) -> GameResult {
+        let animation_proportion = self.animation_proportion(mode);
         let color_format = ctx.gfx.surface_format();
+        let image = Self::render(ctx, &mut self.state, &self.canvas, mode, animation_proportion, color_format)?;
+        out_canvas.draw(&image, glam::vec2(self.canvas.pos.x, self.canvas.pos.y));
+        // drawn separately so they don't end up in exports
+        if let Some(selection) = self.selection{
+            self.draw_selection(ctx, out_canvas, selection)?;
+        }
+        self.draw_minimap(ctx, out_canvas)?;
+        Ok(())
+    }
+
+    fn draw_selection(&self, ctx: &mut Context, out_canvas: &mut graphics::Canvas, selection: Selection) -> GameResult{
+        let (tl, br) = selection.bounds();
+        let view = &self.canvas;
+        let rect = graphics::Rect::new(
+            view.pos.x + tl.x as f32 * view.tile_size - view.offset_x,
+            view.pos.y + tl.y as f32 * view.tile_size - view.offset_y,
+            (br.x - tl.x + 1) as f32 * view.tile_size,
+            (br.y - tl.y + 1) as f32 * view.tile_size
+        );
+        let mut mb = graphics::MeshBuilder::new();
+        mb.rectangle(graphics::DrawMode::fill(), rect, SELECTION_COLOR)?;
+        mb.rectangle(graphics::DrawMode::stroke(SELECTION_OUTLINE_WIDTH), rect, SELECTION_OUTLINE_COLOR)?;
+        let mesh = graphics::Mesh::from_data(ctx, mb.build());
+        out_canvas.draw(&mesh, graphics::DrawParam::default());
+        Ok(())
+    }
+
+    // a group of held tiles snaps to the board, so it shows exactly where they would go
+    pub fn draw_held_tiles(&self, ctx: &mut Context, out_canvas: &mut graphics::Canvas, tiles: &[Tile]) -> GameResult{
+        let mouse_pos = preferences::mouse_position(ctx);
+        let cursor = self.canvas.screen_pos_to_tile(mouse_pos.x, mouse_pos.y);
+        let view = &self.canvas;
+        for tile in tiles.iter(){
+            let screenpos = glam::vec2(
+                view.pos.x + (cursor.x + tile.get_x()) as f32 * view.tile_size - view.offset_x,
+                view.pos.y + (cursor.y + tile.get_y()) as f32 * view.tile_size - view.offset_y
+            );
+            let tile_image = tile.draw(ctx, view.tile_size)?;
+            out_canvas.draw(&mult_alpha(ctx, tile_image, HELD_OBJECT_ALPHA)?, screenpos);
+            if let (true, Some(rank)) = (self.state.rules.priorities.levelled(tile), self.state.rules.priorities.get_rank(tile)){
+                Tile::draw_priority_label(out_canvas, rank, screenpos, view.tile_size);
+            }
+        }
+        Ok(())
+    }
+
+    fn minimap(&self) -> Minimap{
+        let view = &self.canvas;
+        let rect = graphics::Rect::new(
+            view.pos.x + view.pos.w - MINIMAP_SIZE - MINIMAP_MARGIN,
+            view.pos.y + view.pos.h - MINIMAP_SIZE - MINIMAP_MARGIN,
+            MINIMAP_SIZE,
+            MINIMAP_SIZE
+        );
+
+        // everything on the board, and wherever the view is even if that's empty
+        let (tl, br) = BoardCanvas::bounds(&self.state.get_positions(), MINIMAP_BOARD_MARGIN);
+        let left = (tl.x as f32).min(view.offset_x / view.tile_size);
+        let top = (tl.y as f32).min(view.offset_y / view.tile_size);
+        let right = ((br.x + 1) as f32).max((view.offset_x + view.pos.w) / view.tile_size);
+        let bottom = ((br.y + 1) as f32).max((view.offset_y + view.pos.h) / view.tile_size);
+
+        let scale = (rect.w / (right - left)).min(rect.h / (bottom - top));
+        Minimap{
+            rect,
+            // center whatever doesn't fill the minimap
+            left: left - (rect.w / scale - (right - left)) / 2.0,
+            top: top - (rect.h / scale - (bottom - top)) / 2.0,
+            scale
+        }
+    }
+
+    fn draw_minimap(&self, ctx: &mut Context, out_canvas: &mut graphics::Canvas) -> GameResult{
+        let minimap = self.minimap();
+        let mut mb = graphics::MeshBuilder::new();
+        mb.rectangle(graphics::DrawMode::fill(), minimap.rect, MINIMAP_BG_COLOR)?;
+
+        // every tile gets at least a pixel, even when zoomed way out
+        let size = minimap.scale.max(1.0);
+        let tile_rect = |pos: BoardPos| {
+            let screenpos = minimap.to_screen(pos.x as f32, pos.y as f32);
+            graphics::Rect::new(screenpos.x, screenpos.y, size, size)
+        };
+        for tile in self.state.tiles.iter(){
+            mb.rectangle(graphics::DrawMode::fill(), tile_rect(tile.get_pos()), MINIMAP_TILE_COLOR)?;
+        }
+        for blockobject in self.state.blockobjects.iter().chain(self.state.activeblockobjects.iter()){
+            let color = match blockobject.mode{
+                BlockObjectMode::Input => MINIMAP_INPUT_COLOR,
+                BlockObjectMode::Output => MINIMAP_OUTPUT_COLOR,
+                BlockObjectMode::Processing => MINIMAP_BLOCK_COLOR
+            };
+            for pos in blockobject.block_locations(){
+                mb.rectangle(graphics::DrawMode::fill(), tile_rect(pos), color)?;
+            }
+        }
+
+        let view = &self.canvas;
+        let view_tl = minimap.to_screen(view.offset_x / view.tile_size, view.offset_y / view.tile_size);
+        let view_size = minimap.scale / view.tile_size;
+        mb.rectangle(
+            graphics::DrawMode::stroke(MINIMAP_VIEW_WIDTH),
+            graphics::Rect::new(view_tl.x, view_tl.y, view.pos.w * view_size, view.pos.h * view_size),
+            MINIMAP_VIEW_COLOR
+        )?;
+
+        let mesh = graphics::Mesh::from_data(ctx, mb.build());
+        out_canvas.draw(&mesh, graphics::DrawParam::default());
+        Ok(())
+    }
+
+    fn animation_proportion(&self, mode: &LevelMode) -> f32{
+        match mode {
+            LevelMode::Running => self.state.animation_timer.as_secs_f32()/self.state.animation_duration.as_secs_f32(),
+            LevelMode::Error => 0.3,
+            _ => 1.0
+        }
+    }
+
+    // draw the board into a new image the size of the view
+    // animation_proportion is how far through the current tick's animations we are
+    fn render(
+        ctx: &mut Context,
+        state: &mut BoardState,
+        view: &BoardCanvas,
+        mode: &LevelMode,
+        animation_proportion: f32,
+        color_format: graphics::ImageFormat
+    ) -> GameResult<graphics::Image>{
         let image = graphics::Image::new_canvas_image(
             ctx, color_format,
-            self.canvas.pos.w as u32,
-            self.canvas.pos.h as u32,
+            view.pos.w as u32,
+            view.pos.h as u32,
             1
         );
-        let mut image_canvas = graphics::Canvas::from_image(ctx, image.clone(), BOARD_BG_COLOR);
+        let mut image_canvas = graphics::Canvas::from_image(ctx, image.clone(), preferences::theme().board_bg);
 
         // empty tiles
-        let empty_tile_image = asset_cache::get_scaled_image(ctx, "empty_tile".to_string(), self.canvas.tile_size)?;
+        let empty_tile_image = asset_cache::get_scaled_image(ctx, "empty_tile".to_string(), view.tile_size)?;
 
         let mut empty_tile_ia = graphics::InstanceArray::new(ctx, empty_tile_image);
 
-        let tilex_min = (self.canvas.offset_x/self.canvas.tile_size).floor() as i32;
-        let tilex_max = ((self.canvas.offset_x+self.canvas.pos.w)/self.canvas.tile_size).ceil() as i32;
-        let tiley_min = (self.canvas.offset_y/self.canvas.tile_size).floor() as i32;
-        let tiley_max = ((self.canvas.offset_y+self.canvas.pos.h)/self.canvas.tile_size).ceil() as i32;
+        let tilex_min = (view.offset_x/view.tile_size).floor() as i32;
+        let tilex_max = ((view.offset_x+view.pos.w)/view.tile_size).ceil() as i32;
+        let tiley_min = (view.offset_y/view.tile_size).floor() as i32;
+        let tiley_max = ((view.offset_y+view.pos.h)/view.tile_size).ceil() as i32;
 
         for tiley in tiley_min..tiley_max {
             for tilex in tilex_min..tilex_max {
                 empty_tile_ia.push(
                     glam::vec2(
-                        tilex as f32 * self.canvas.tile_size - self.canvas.offset_x,
-                        tiley as f32 * self.canvas.tile_size - self.canvas.offset_y
+                        tilex as f32 * view.tile_size - view.offset_x,
+                        tiley as f32 * view.tile_size - view.offset_y
                     ).into()
                 );
             }
@@ -109,39 +307,40 @@ impl Board{
         // I don't think an instance array would actually help here, given that rotations are different images
         // however, I could draw the bases first and then the symbols if I need the speed
 
-        for tile in self.state.tiles.iter(){
+        for tile in state.tiles.iter(){
             if tile.get_x() >= tilex_min && tile.get_x() <= tilex_max &&
                 tile.get_y() >= tiley_min && tile.get_y() <= tiley_max{
 
-                image_canvas.draw(
-                    &tile.draw(ctx, self.canvas.tile_size)?,
-                    glam::vec2(
-                        tile.get_x() as f32 * self.canvas.tile_size - self.canvas.offset_x,
-                        tile.get_y() as f32 * self.canvas.tile_size - self.canvas.offset_y
-                    )
-                )
+                let tile_screenpos = glam::vec2(
+                    tile.get_x() as f32 * view.tile_size - view.offset_x,
+                    tile.get_y() as f32 * view.tile_size - view.offset_y
+                );
+                image_canvas.draw(&tile.draw(ctx, view.tile_size)?, tile_screenpos);
+                if state.rules.priorities.levelled(tile){
+                    if let Some(rank) = state.rules.priorities.get_rank(tile){
+                        Tile::draw_priority_label(&mut image_canvas, rank, tile_screenpos, view.tile_size);
+                    }
+                }
             }
         }
 
         // blocks
-        for blockobject in self.state.blockobjects.iter_mut().chain(self.state.activeblockobjects.iter_mut()){
-            let bo_image = blockobject.draw(ctx, self.canvas.tile_size)?;
+        let n_inactive = state.blockobjects.len();
+        for (i, blockobject) in state.blockobjects.iter_mut().chain(state.activeblockobjects.iter_mut()).enumerate(){
+            let bo_image = blockobject.draw(ctx, view.tile_size)?;
             let bo_pos = blockobject.get_top_left()?;
             let mut screenpos = glam::vec2(
-                bo_pos.x as f32 * self.canvas.tile_size - self.canvas.offset_x,
-                bo_pos.y as f32 * self.canvas.tile_size - self.canvas.offset_y
+                bo_pos.x as f32 * view.tile_size - view.offset_x,
+                bo_pos.y as f32 * view.tile_size - view.offset_y
             );
 
-            let animation_proportion = match mode {
-                LevelMode::Running => self.state.animation_timer.as_secs_f32()/self.state.animation_duration.as_secs_f32(),
-                LevelMode::Error => 0.3,
-                _ => 1.0
-            };
+            // a second copy to draw, only used while teleporting
+            let mut ghost_param: Option<graphics::DrawParam> = None;
 
             let param: graphics::DrawParam = match blockobject.anim{
                 BlockObjectAnimation::Translation { x, y } => {
-                    screenpos.x += x * self.canvas.tile_size * (animation_proportion - 1.0);
-                    screenpos.y += y * self.canvas.tile_size * (animation_proportion - 1.0);
+                    screenpos.x += x * view.tile_size * (animation_proportion - 1.0);
+                    screenpos.y += y * view.tile_size * (animation_proportion - 1.0);
                     screenpos.into()
                 },
                 BlockObjectAnimation::Rotation { theta , around } => {
@@ -153,8 +352,8 @@ impl Board{
                     let rot = theta*(1.0 - animation_proportion);
                     let bo_tl = blockobject.get_top_left()?;
                     let around_vec = glam::vec2(
-                        self.canvas.tile_size * ((around.x - bo_tl.x) as f32 + 0.5),
-                        self.canvas.tile_size * ((around.y - bo_tl.y) as f32 + 0.5)
+                        view.tile_size * ((around.x - bo_tl.x) as f32 + 0.5),
+                        view.tile_size * ((around.y - bo_tl.y) as f32 + 0.5)
                     );
                     let rot_mat = glam::Mat2::from_angle(rot);
                     let rotated_vec = rot_mat * around_vec;
@@ -163,57 +362,232 @@ impl Board{
                     let param: graphics::DrawParam= screenpos.into();
                     param.rotation(rot)
                 },
+                BlockObjectAnimation::Mirror { vertical, around } => {
+                    // flip the image over the axis by scaling it from -1 back up to 1
+                    let scale = 2.0*animation_proportion - 1.0;
+                    if vertical{
+                        let axis = (around.x as f32 + 0.5) * view.tile_size - view.offset_x;
+                        screenpos.x = axis + scale * (screenpos.x - axis);
+                        let param: graphics::DrawParam = screenpos.into();
+                        param.scale(glam::vec2(scale, 1.0))
+                    }else{
+                        let axis = (around.y as f32 + 0.5) * view.tile_size - view.offset_y;
+                        screenpos.y = axis + scale * (screenpos.y - axis);
+                        let param: graphics::DrawParam = screenpos.into();
+                        param.scale(glam::vec2(1.0, scale))
+                    }
+                },
+                BlockObjectAnimation::Teleport { x, y } => {
+                    // fade out where it was while fading in where it is now
+                    ghost_param = Some(graphics::DrawParam::default()
+                        .dest(glam::vec2(
+                            screenpos.x - x * view.tile_size,
+                            screenpos.y - y * view.tile_size
+                        ))
+                        .color(graphics::Color::new(1.0, 1.0, 1.0, 1.0 - animation_proportion)));
+                    graphics::DrawParam::default()
+                        .dest(screenpos)
+                        .color(graphics::Color::new(1.0, 1.0, 1.0, animation_proportion))
+                },
                 BlockObjectAnimation::Output => {
                     // TODO: think of some fun animation to do here
                     screenpos.into()
                 }
             };
 
-            match (blockobject.mode, mode){
+            let bo_image = match (blockobject.mode, mode){
                 (BlockObjectMode::Input, LevelMode::Building) =>
-                    image_canvas.draw(&mult_alpha(ctx, bo_image, BUILDING_BLOCKOBJECT_ALPHA)?, param),
+                    Some(mult_alpha(ctx, bo_image, BUILDING_BLOCKOBJECT_ALPHA)?),
                 (BlockObjectMode::Output, _) =>
-                    image_canvas.draw(&bo_image, param),
+                    Some(bo_image),
                 (BlockObjectMode::Processing, LevelMode::Running) =>
-                    image_canvas.draw(&mult_alpha(ctx, bo_image, RUNNING_BLOCKOBJECT_ALPHA)?, param),
+                    Some(mult_alpha(ctx, bo_image, RUNNING_BLOCKOBJECT_ALPHA)?),
+                // whatever caused the error stands out
+                (BlockObjectMode::Processing, LevelMode::Error) if i >= n_inactive && state.error_blockobjects.contains(&(i - n_inactive)) =>
+                    Some(bo_image),
                 (BlockObjectMode::Processing, LevelMode::Error) =>
-                    image_canvas.draw(&mult_alpha(ctx, bo_image, RUNNING_BLOCKOBJECT_ALPHA)?, param),
-                _default => ()
+                    Some(mult_alpha(ctx, bo_image, RUNNING_BLOCKOBJECT_ALPHA)?),
+                _default => None
+            };
+
+            if let Some(bo_image) = bo_image{
+                image_canvas.draw(&bo_image, param);
+                if let Some(ghost_param) = ghost_param{
+                    image_canvas.draw(&bo_image, ghost_param);
+                }
             }
 
         }
 
         if let LevelMode::Error = mode{
-            for error_pos in self.state.error_locs.iter(){
+            for error_pos in state.error_locs.iter(){
                 if error_pos.x >= tilex_min && error_pos.x <= tilex_max &&
                     error_pos.y >= tiley_min && error_pos.y <= tiley_max{
 
                     image_canvas.draw(
-                        &asset_cache::get_scaled_image(ctx, "error_tile".to_string(), self.canvas.tile_size)?,
+                        &asset_cache::get_scaled_image(ctx, "error_tile".to_string(), view.tile_size)?,
                         glam::vec2(
-                            error_pos.x as f32 * self.canvas.tile_size - self.canvas.offset_x,
-                            error_pos.y as f32 * self.canvas.tile_size - self.canvas.offset_y
+                            error_pos.x as f32 * view.tile_size - view.offset_x,
+                            error_pos.y as f32 * view.tile_size - view.offset_y
                         )
                     )
                 }
             }
         }
 
+        if let (LevelMode::Error, Some(diagnostics)) = (mode, &state.split_diagnostics){
+            Self::draw_split_diagnostics(ctx, view, &mut image_canvas, diagnostics)?;
+        }
+
         image_canvas.finish(ctx)?;
+        Ok(image)
+    }
 
-        out_canvas.draw(&image, glam::vec2(self.canvas.pos.x, self.canvas.pos.y));
+    // play the solution on the board from the start, and write every frame of it to disk
+    // this uses a copy of the board, so the player's board isn't touched
+    // nothing gets drawn yet, RunExport::step does that a few frames at a time so the window keeps going
+    pub fn export_run(&self, level: &str, format: ExportFormat) -> GameResult<RunExport>{
+        // run it once without drawing to find out what has to fit in the frame, and how long it is
+        let mut dry_run = self.state.clone();
+        dry_run.process_start()?;
+        let mut positions = dry_run.get_positions();
+        while dry_run.game_ticks < EXPORT_MAX_TICKS{
+            let result = dry_run.process_step();
+            positions.extend(dry_run.get_positions());
+            if !matches!(result, Ok(false)){
+                break;
+            }
+        }
+
+        let mut state = self.state.clone();
+        state.process_start()?;
+        Ok(RunExport{
+            exporter: Exporter::new(level, format, EXPORT_SIZE.0, EXPORT_SIZE.1)?,
+            state,
+            view: BoardCanvas::fit(&positions, EXPORT_SIZE),
+            mode: LevelMode::Running,
+            animation_proportion: 1.0,
+            total_ticks: dry_run.game_ticks,
+            done: false
+        })
+    }
+
+    // canvases only work inside a frame and nothing gets drawn until it ends, so anything rendering
+    // outside of draw (snapshots, exports, tests) gets a frame of its own before reading the pixels back
+    fn render_pixels(
+        ctx: &mut Context,
+        state: &mut BoardState,
+        view: &BoardCanvas,
+        mode: &LevelMode,
+        animation_proportion: f32,
+        color_format: graphics::ImageFormat
+    ) -> GameResult<Vec<u8>>{
+        ctx.gfx.begin_frame()?;
+        let image = Self::render(ctx, state, view, mode, animation_proportion, color_format);
+        ctx.gfx.end_frame()?;
+        image?.to_pixels(ctx)
+    }
+
+    // draw any board state straight to a png, without a window showing it
+    pub fn render_png(
+        ctx: &mut Context,
+        state: &mut BoardState,
+        view: &BoardCanvas,
+        mode: &LevelMode,
+        animation_proportion: f32,
+        path: &Path
+    ) -> GameResult{
+        // reading pixels back needs rows that are a multiple of 64 pixels, so draw wider and cut it down after
+        let (width, height) = (view.pos.w as u32, view.pos.h as u32);
+        let mut padded = view.clone();
+        padded.pos.w = width.next_multiple_of(64) as f32;
+
+        let color_format = ctx.gfx.surface_format();
+        let pixels = Self::render_pixels(ctx, state, &padded, mode, animation_proportion, color_format)?;
+        let pixels = rgba_image(pixels, color_format, padded.pos.w as u32, height)?;
+        image::imageops::crop_imm(&pixels, 0, 0, width, height).to_image()
+            .save(path).map_err(image_error)
+    }
+
+    // save exactly what is on screen, for bug reports
+    pub fn snapshot(&mut self, ctx: &mut Context, mode: &LevelMode, level: &str) -> GameResult<PathBuf>{
+        let path = timestamped_path(SNAPSHOT_DIR, level)?.with_extension("png");
+        let animation_proportion = self.animation_proportion(mode);
+        Self::render_png(ctx, &mut self.state, &self.canvas, mode, animation_proportion, &path)?;
+        Ok(path)
+    }
+
+    // overlays showing why a split failed
+    fn draw_split_diagnostics(ctx: &mut Context, view: &BoardCanvas, image_canvas: &mut graphics::Canvas, diagnostics: &SplitDiagnostics) -> GameResult{
+        let ts = view.tile_size;
+        let to_screen = |x: f32, y: f32| glam::vec2(x * ts - view.offset_x, y * ts - view.offset_y);
+        let mut mb = graphics::MeshBuilder::new();
+
+        for pos in diagnostics.wrong_side.iter(){
+            let tl = to_screen(pos.x as f32, pos.y as f32);
+            mb.rectangle(graphics::DrawMode::fill(), graphics::Rect::new(tl.x, tl.y, ts, ts), WRONG_SIDE_COLOR)?;
+        }
+
+        // a strip along the edge of the block that faces the seam
+        for (pos, away) in diagnostics.clashing.iter(){
+            let tl = to_screen(pos.x as f32, pos.y as f32);
+            let strip = ts * CLASHING_WIDTH;
+            let rect = match away{
+                Direction::Right => graphics::Rect::new(tl.x, tl.y, strip, ts),
+                Direction::Left => graphics::Rect::new(tl.x + ts - strip, tl.y, strip, ts),
+                Direction::Down => graphics::Rect::new(tl.x, tl.y, ts, strip),
+                Direction::Up => graphics::Rect::new(tl.x, tl.y + ts - strip, ts, strip)
+            };
+            mb.rectangle(graphics::DrawMode::fill(), rect, CLASHING_COLOR)?;
+        }
+
+        for seam in diagnostics.seams.iter(){
+            let line = (seam.line + 1) as f32;
+            let (start, end) = if seam.vertical{
+                (to_screen(line, seam.start as f32), to_screen(line, (seam.end + 1) as f32))
+            }else{
+                (to_screen(seam.start as f32, line), to_screen((seam.end + 1) as f32, line))
+            };
+            mb.line(&[start, end], ts * SEAM_WIDTH, SEAM_COLOR)?;
+        }
+
+        let mesh = graphics::Mesh::from_data(ctx, mb.build());
+        image_canvas.draw(&mesh, graphics::DrawParam::default());
         Ok(())
     }
 
     pub fn mouse_button_down_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         button: MouseButton,
         x: f32,
-        y: f32
+        y: f32,
+        mode: &LevelMode,
+        held: &Holding
     ) -> GameResult{
-        if self.canvas.pos.contains(glam::vec2(x, y)) && button == MouseButton::Left{
-            self.mouse_down = true;
+        // dragging on the minimap shouldn't pan the board
+        let on_minimap = self.minimap().rect.contains(glam::vec2(x, y));
+        if !self.canvas.pos.contains(glam::vec2(x, y)) || on_minimap{
+            return Ok(());
+        }
+        let pos = self.canvas.screen_pos_to_tile(x, y);
+        let building = *mode == LevelMode::Building;
+        match button{
+            // plain dragging pans, so selecting needs ctrl
+            MouseButton::Left if building && ctx.keyboard.is_mod_active(KeyMods::CTRL) => {
+                self.selection = Some(Selection{start: pos, end: pos, dragging: true});
+            },
+            // nothing gets painted until the cursor leaves this cell, so clicking still works like before
+            MouseButton::Left if building && matches!(held, Holding::Tile{..}) => {
+                self.stroke = Some(Stroke{erase: false, last: pos, painted: false});
+            },
+            MouseButton::Right if building => {
+                self.state.remove_tile(pos);
+                self.stroke = Some(Stroke{erase: true, last: pos, painted: true});
+            },
+            // middle dragging always pans, even while holding something
+            MouseButton::Left | MouseButton::Middle => self.mouse_down = Some(button),
+            _ => ()
         }
         Ok(())
     }
@@ -225,8 +599,20 @@ impl Board{
         _x: f32,
         _y: f32
     ) -> GameResult{
-        if button == MouseButton::Left{
-            self.mouse_down = false;
+        // only letting go of the button that started something ends it
+        if self.mouse_down == Some(button){
+            self.mouse_down = None;
+        }
+        if let Some(stroke) = &self.stroke{
+            let stroke_button = if stroke.erase {MouseButton::Right} else {MouseButton::Left};
+            if button == stroke_button{
+                self.stroke = None;
+            }
+        }
+        if let Some(selection) = &mut self.selection{
+            if button == MouseButton::Left{
+                selection.dragging = false;
+            }
         }
         Ok(())
     }
@@ -237,16 +623,34 @@ impl Board{
             button: MouseButton,
             x: f32,
             y: f32,
-            held: &mut Holding
+            held: &mut Holding,
+            mode: &LevelMode
     ) -> GameResult{
-        if self.canvas.pos.contains(glam::vec2(x, y)) && button == MouseButton::Left{
+        // the button went up at the end of a paint stroke, not a click
+        if matches!(self.stroke, Some(Stroke{painted: true, ..})){
+            return Ok(());
+        }
+        let minimap = self.minimap();
+        if minimap.rect.contains(glam::vec2(x, y)) && button == MouseButton::Left{
+            // jump to wherever was clicked
+            let (board_x, board_y) = minimap.to_board(x, y);
+            self.canvas.center_on(board_x, board_y);
+        }else if self.canvas.pos.contains(glam::vec2(x, y)) && button == MouseButton::Left && *mode == LevelMode::Building{
             let tilepos = self.canvas.screen_pos_to_tile(x, y);
             match held{
-                Holding::Tile { tile } => {
-                    self.state.place_tile(tile.get_type(), tilepos, tile.get_dir());
-                },
+                Holding::Tile { tile } => self.state.place_tile_like(tile, tilepos, tile.get_dir()),
                 Holding::BlockObject { blockobject } => self.state.place_blockobject(blockobject.clone(), tilepos)?,
-                Holding::None => ()
+                Holding::Tiles { tiles } => {
+                    for tile in tiles.iter(){
+                        let mut tile = tile.clone();
+                        tile.apply(MovementType::Teleport{dx: tilepos.x, dy: tilepos.y});
+                        self.state.insert_tile(tile);
+                    }
+                },
+                // clicking somewhere else gets rid of the selection, ctrl clicking makes a new one
+                Holding::None => if !ctx.keyboard.is_mod_active(KeyMods::CTRL){
+                    self.selection = None;
+                }
             }
             // NOTE: when I implement blockobject, make sure shift-placing it doesn't break anything
             if !ctx.keyboard.is_mod_active(KeyMods::SHIFT){
@@ -259,34 +663,172 @@ impl Board{
     pub fn mouse_motion_event(
         &mut self,
         _ctx: &mut Context,
-        _x: f32,
-        _y: f32,
+        x: f32,
+        y: f32,
         dx: f32,
-        dy: f32
+        dy: f32,
+        held: &Holding
     ) -> GameResult{
-        if self.mouse_down{
+        if self.stroke.is_some(){
+            let pos = self.canvas.screen_pos_to_tile(x, y);
+            self.continue_stroke(pos, held);
+        }
+        if let Some(selection) = &mut self.selection{
+            if selection.dragging{
+                selection.end = self.canvas.screen_pos_to_tile(x, y);
+            }
+        }
+        if self.mouse_down.is_some(){
             self.canvas.offset_x -= dx;
             self.canvas.offset_y -= dy;
         }
         Ok(())
     }
 
-    pub fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeated: bool) -> GameResult {
-        let mouse_pos = ctx.mouse.position();
-        let tile_pos = self.canvas.screen_pos_to_tile(mouse_pos.x, mouse_pos.y);
-        if input.keycode == Some(KeyCode::R) {
-            if input.mods.contains(KeyMods::SHIFT){
-                self.state.rotate_tile_ccw(tile_pos);
+    // walk the stroke one cell at a time to wherever the cursor is now, so fast drags don't leave gaps
+    fn continue_stroke(&mut self, to: BoardPos, held: &Holding){
+        let Some(stroke) = &mut self.stroke else{
+            return;
+        };
+        while stroke.last != to{
+            let (dx, dy) = (to.x - stroke.last.x, to.y - stroke.last.y);
+            // step along whichever way is further, which stays close to a straight line
+            let dir = if dx.abs() >= dy.abs(){
+                if dx > 0 {Direction::Right} else {Direction::Left}
             }else{
-                self.state.rotate_tile_cw(tile_pos);
+                if dy > 0 {Direction::Down} else {Direction::Up}
+            };
+            let (step_x, step_y) = dir.to_offset();
+            let next = BoardPos{x: stroke.last.x + step_x, y: stroke.last.y + step_y};
+
+            if stroke.erase{
+                self.state.remove_tile(next);
+            }else if let Holding::Tile{tile} = held{
+                // tiles that point somewhere follow the drag, so the one we just left turns to face this way
+                let rotatable = tile.get_type().rotatable();
+                let dir = if rotatable {dir} else {tile.get_dir()};
+                if !stroke.painted || rotatable{
+                    self.state.place_tile_like(tile, stroke.last, dir);
+                }
+                self.state.place_tile_like(tile, next, dir);
+                stroke.painted = true;
             }
-        }else if input.keycode == Some(KeyCode::D) {
+            stroke.last = next;
+        }
+    }
+
+    pub fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeated: bool, mode: &LevelMode, held: &mut Holding) -> GameResult {
+        let mouse_pos = preferences::mouse_position(ctx);
+        let tile_pos = self.canvas.screen_pos_to_tile(mouse_pos.x, mouse_pos.y);
+        let action = keymap::action(&input);
+        // zooming works while it's running too, everything else is for editing
+        if action == Some(Action::ZoomIn){
+            self.canvas.zoom(true, mouse_pos.x, mouse_pos.y);
+        }else if action == Some(Action::ZoomOut){
+            self.canvas.zoom(false, mouse_pos.x, mouse_pos.y);
+        }else if action == Some(Action::FitAll){
+            self.canvas.frame(&self.state.get_positions());
+        }else if action == Some(Action::GoToOrigin){
+            self.canvas.center_on(0.5, 0.5);
+        }else if *mode != LevelMode::Building || self.selection_key_event(action, tile_pos, held){
+            return Ok(());
+        }else if action == Some(Action::RotateCw){
+            self.state.rotate_tile_cw(tile_pos);
+        }else if action == Some(Action::RotateCcw){
+            self.state.rotate_tile_ccw(tile_pos);
+        }else if action == Some(Action::RemoveTile){
             self.state.remove_tile(tile_pos);
+        }else if let Some(number) = input.keycode.and_then(number_key_value){
+            self.state.set_tile_number(tile_pos, number);
+        }
+        Ok(())
+    }
+
+    // number keys change these instead of picking from the sidebar
+    pub fn numbered_tile_hovered(&mut self, ctx: &mut Context) -> bool{
+        let mouse_pos = preferences::mouse_position(ctx);
+        self.canvas.pos.contains(mouse_pos)
+            && self.state.numbered_tile_at(self.canvas.screen_pos_to_tile(mouse_pos.x, mouse_pos.y))
+    }
+
+    // copying and pasting, and everything that can be done to the selected tiles
+    // returns whether the key got used
+    fn selection_key_event(&mut self, action: Option<Action>, cursor: BoardPos, held: &mut Holding) -> bool{
+        if action == Some(Action::Paste){
+            if !self.clipboard.is_empty(){
+                *held = Holding::Tiles{tiles: self.clipboard.clone()};
+            }
+            return true;
+        }
+        // whatever is being held gets the keys instead
+        let (Some(selection), Holding::None) = (self.selection, &held) else{
+            return false;
+        };
+        let (tl, br) = selection.bounds();
+        match action{
+            Some(Action::Copy) => self.clipboard = self.state.copy_tiles(tl, br, tl),
+            Some(Action::Cut) => {
+                self.clipboard = self.state.take_tiles(tl, br, tl);
+                self.selection = None;
+            },
+            Some(Action::Duplicate) => *held = Holding::Tiles{tiles: self.state.copy_tiles(tl, br, cursor)},
+            Some(Action::MoveSelection) => {
+                *held = Holding::Tiles{tiles: self.state.take_tiles(tl, br, cursor)};
+                self.selection = None;
+            },
+            Some(Action::RotateCw) => self.transform_selection(MovementType::Rotation{quarter_turns: 1, around: tl}),
+            Some(Action::RotateCcw) => self.transform_selection(MovementType::Rotation{quarter_turns: -1, around: tl}),
+            // flipping left to right mirrors across a vertical line
+            Some(Action::FlipHorizontal) => self.transform_selection(MovementType::Mirror{vertical: true, around: tl}),
+            Some(Action::FlipVertical) => self.transform_selection(MovementType::Mirror{vertical: false, around: tl}),
+            Some(Action::DeleteSelection) => {
+                self.state.take_tiles(tl, br, tl);
+                self.selection = None;
+            },
+            _ => return false
+        }
+        true
+    }
+
+    // turn or flip the selected tiles where they are, keeping the top left corner of the selection in place
+    fn transform_selection(&mut self, movement: MovementType){
+        let Some(selection) = self.selection else{
+            return;
+        };
+        let (tl, br) = selection.bounds();
+        let (start, end) = (movement.apply(tl), movement.apply(br));
+        let new_tl = BoardPos{x: start.x.min(end.x), y: start.y.min(end.y)};
+        let shift_back = MovementType::Teleport{dx: tl.x - new_tl.x, dy: tl.y - new_tl.y};
+
+        for mut tile in self.state.take_tiles(tl, br, BoardPos{x: 0, y: 0}){
+            tile.apply(movement);
+            tile.apply(shift_back);
+            self.state.insert_tile(tile);
+        }
+        self.selection = Some(Selection{
+            start: shift_back.apply(start),
+            end: shift_back.apply(end),
+            dragging: false
+        });
+    }
+
+    pub fn mouse_wheel_event(&mut self, ctx: &mut Context, _x: f32, y: f32, mode: &LevelMode) -> GameResult {
+        let mouse_pos = preferences::mouse_position(ctx);
+        if self.canvas.pos.contains(mouse_pos) && y != 0.0{
+            let tile_pos = self.canvas.screen_pos_to_tile(mouse_pos.x, mouse_pos.y);
+            // scrolling over a numbered tile changes the number, ctrl always zooms (touchpads send pinches as ctrl+scroll)
+            let numbered = *mode == LevelMode::Building && self.state.numbered_tile_at(tile_pos);
+            if numbered && !ctx.keyboard.is_mod_active(KeyMods::CTRL){
+                self.state.change_tile_number(tile_pos, if y > 0.0 {1} else {-1});
+            }else{
+                self.canvas.zoom(y > 0.0, mouse_pos.x, mouse_pos.y);
+            }
         }
         Ok(())
     }
 
     pub fn process_start(&mut self) -> GameResult{
+        self.selection = None;
         // immediately start the first step
         self.state.animation_timer = self.state.animation_duration;
         self.state.process_start()
@@ -299,6 +841,60 @@ impl Board{
     pub fn num_blockobjects(&self) -> usize{
         self.state.blockobjects.len()
     }
+
+    pub fn to_solution(&self, level: &str, level_blockobjects: &[BlockObject]) -> GameResult<SerializedSolution>{
+        self.state.to_solution(level, level_blockobjects)
+    }
+
+    pub fn get_replay_ticks(&self) -> &[Vec<ReplayEvent>]{
+        self.state.get_replay_ticks()
+    }
+}
+
+impl RunExport{
+    // draws up to the given number of frames, true once every frame up to the end is written
+    pub fn step(&mut self, ctx: &mut Context, frames: i32) -> GameResult<bool>{
+        let color_format = ctx.gfx.surface_format();
+        let frame_time = ANIMATION_DURATION / EXPORT_FRAMES_PER_TICK as f32;
+        for _ in 0..frames{
+            if self.done || self.state.game_ticks >= EXPORT_MAX_TICKS{
+                return Ok(true);
+            }
+            let pixels = Board::render_pixels(ctx, &mut self.state, &self.view, &self.mode, self.animation_proportion, color_format)?;
+            self.exporter.add_frame(pixels, color_format, frame_time)?;
+            if self.animation_proportion < 1.0{
+                self.animation_proportion += 1.0 / EXPORT_FRAMES_PER_TICK as f32;
+                continue;
+            }
+
+            match self.state.process_step(){
+                Ok(false) => {
+                    self.animation_proportion = 1.0 / EXPORT_FRAMES_PER_TICK as f32;
+                },
+                Ok(true) => self.done = true,
+                Err(sim_err) => {
+                    self.state.set_error(&sim_err);
+                    self.mode = LevelMode::Error;
+                    self.done = true;
+                }
+            }
+        }
+        Ok(self.done || self.state.game_ticks >= EXPORT_MAX_TICKS)
+    }
+
+    // out of 100
+    pub fn progress(&self) -> i32{
+        (self.state.game_ticks * 100 / self.total_ticks.max(1)).min(100)
+    }
+
+    // hold on the end so it's clear where the loop is, then write it out
+    pub fn finish(mut self, ctx: &mut Context) -> GameResult<PathBuf>{
+        let color_format = ctx.gfx.surface_format();
+        let proportion = if self.mode == LevelMode::Error {0.3} else {1.0};
+        let pixels = Board::render_pixels(ctx, &mut self.state, &self.view, &self.mode, proportion, color_format)?;
+        self.exporter.add_frame(pixels, color_format, EXPORT_END_HOLD)?;
+        Ok(self.exporter.finish())
+    }
 }
 
 impl BoardCanvas{
@@ -311,6 +907,94 @@ impl BoardCanvas{
         }
     }
 
+    // keeps whatever was in the middle in the middle
+    fn resize(&mut self, screenpos: graphics::Rect){
+        self.offset_x += (self.pos.w - screenpos.w) / 2.0;
+        self.offset_y += (self.pos.h - screenpos.h) / 2.0;
+        self.pos = screenpos;
+    }
+
+    // columns by rows tiles, starting from top_left
+    pub fn viewport(top_left: BoardPos, columns: u32, rows: u32, tile_size: f32) -> BoardCanvas{
+        BoardCanvas{
+            pos: graphics::Rect::new(0.0, 0.0, columns as f32 * tile_size, rows as f32 * tile_size),
+            tile_size,
+            offset_x: top_left.x as f32 * tile_size,
+            offset_y: top_left.y as f32 * tile_size
+        }
+    }
+
+    // the top left and bottom right tiles of every position, with a margin
+    // just the origin if there aren't any positions
+    fn bounds(positions: &[BoardPos], margin: i32) -> (BoardPos, BoardPos){
+        let xmin = positions.iter().map(|pos| pos.x).min().unwrap_or(0) - margin;
+        let xmax = positions.iter().map(|pos| pos.x).max().unwrap_or(0) + margin;
+        let ymin = positions.iter().map(|pos| pos.y).min().unwrap_or(0) - margin;
+        let ymax = positions.iter().map(|pos| pos.y).max().unwrap_or(0) + margin;
+        (BoardPos{x: xmin, y: ymin}, BoardPos{x: xmax, y: ymax})
+    }
+
+    // a view of the given size that fits every position, with a margin
+    pub fn fit(positions: &[BoardPos], size: (u32, u32)) -> BoardCanvas{
+        let (tl, br) = Self::bounds(positions, EXPORT_MARGIN);
+        let (width, height) = (size.0 as f32, size.1 as f32);
+        let columns = (br.x - tl.x + 1) as f32;
+        let rows = (br.y - tl.y + 1) as f32;
+
+        let mut view = BoardCanvas{
+            pos: graphics::Rect::new(0.0, 0.0, width, height),
+            // a huge layout gets squashed to a pixel a tile instead of vanishing
+            tile_size: (width / columns).min(height / rows).floor().max(1.0),
+            offset_x: 0.0,
+            offset_y: 0.0
+        };
+        view.center_on(tl.x as f32 + columns / 2.0, tl.y as f32 + rows / 2.0);
+        view
+    }
+
+    // zoom and pan this view so every position is on screen, sticking to the usual zoom levels
+    fn frame(&mut self, positions: &[BoardPos]){
+        let (tl, br) = Self::bounds(positions, FRAME_MARGIN);
+        let columns = (br.x - tl.x + 1) as f32;
+        let rows = (br.y - tl.y + 1) as f32;
+        self.tile_size = ZOOM_LEVELS.iter().rev()
+            .find(|size| columns * **size <= self.pos.w && rows * **size <= self.pos.h)
+            .copied()
+            .unwrap_or(ZOOM_LEVELS[0]);
+        self.center_on(tl.x as f32 + columns / 2.0, tl.y as f32 + rows / 2.0);
+    }
+
+    // put a board position (in tiles) in the middle of the view
+    fn center_on(&mut self, x: f32, y: f32){
+        self.offset_x = x * self.tile_size - self.pos.w / 2.0;
+        self.offset_y = y * self.tile_size - self.pos.h / 2.0;
+    }
+
+    // step to the next zoom level, keeping whatever is under (x, y) on the screen in place
+    // if (x, y) isn't on the board it zooms around the middle instead
+    fn zoom(&mut self, zoom_in: bool, x: f32, y: f32){
+        let new_size = if zoom_in{
+            ZOOM_LEVELS.iter().find(|size| **size > self.tile_size)
+        }else{
+            ZOOM_LEVELS.iter().rev().find(|size| **size < self.tile_size)
+        };
+        let Some(new_size) = new_size.copied() else{
+            return;
+        };
+
+        let (x, y) = if self.pos.contains(glam::vec2(x, y)){
+            (x - self.pos.x, y - self.pos.y)
+        }else{
+            (self.pos.w / 2.0, self.pos.h / 2.0)
+        };
+        // the point under the cursor, measured in tiles
+        let board_x = (x + self.offset_x) / self.tile_size;
+        let board_y = (y + self.offset_y) / self.tile_size;
+        self.tile_size = new_size;
+        self.offset_x = board_x * new_size - x;
+        self.offset_y = board_y * new_size - y;
+    }
+
     fn screen_pos_to_tile(&self, x: f32, y: f32) -> BoardPos{
         let true_x = x + self.offset_x - self.pos.x;
         let true_y = y + self.offset_y - self.pos.y;
@@ -321,8 +1005,35 @@ impl BoardCanvas{
     }
 }
 
+impl Selection{
+    // the top left and bottom right corners
+    fn bounds(&self) -> (BoardPos, BoardPos){
+        (
+            BoardPos{x: self.start.x.min(self.end.x), y: self.start.y.min(self.end.y)},
+            BoardPos{x: self.start.x.max(self.end.x), y: self.start.y.max(self.end.y)}
+        )
+    }
+}
+
+impl Minimap{
+    // board positions are in tiles
+    fn to_screen(&self, x: f32, y: f32) -> glam::Vec2{
+        glam::vec2(
+            self.rect.x + (x - self.left) * self.scale,
+            self.rect.y + (y - self.top) * self.scale
+        )
+    }
+
+    fn to_board(&self, x: f32, y: f32) -> (f32, f32){
+        (
+            self.left + (x - self.rect.x) / self.scale,
+            self.top + (y - self.rect.y) / self.scale
+        )
+    }
+}
+
 impl BoardState{
-    fn new() -> BoardState {
+    pub fn new() -> BoardState {
         BoardState{
             animation_duration: Duration::from_secs_f32(ANIMATION_DURATION),
             animation_timer: Duration::ZERO,
@@ -330,7 +1041,11 @@ impl BoardState{
             tiles: Vec::new(),
             blockobjects: Vec::new(),
             activeblockobjects: Vec::new(),
-            error_locs: Vec::new()
+            rules: LevelRules::default(),
+            error_locs: Vec::new(),
+            error_blockobjects: Vec::new(),
+            split_diagnostics: None,
+            replay_ticks: Vec::new()
         }
     }
 
@@ -347,6 +1062,118 @@ impl BoardState{
         found_index
     }
 
+    pub fn set_rules(&mut self, rules: LevelRules){
+        self.rules = rules;
+    }
+
+    pub fn get_game_ticks(&self) -> i32{
+        self.game_ticks
+    }
+
+    pub fn get_replay_ticks(&self) -> &[Vec<ReplayEvent>]{
+        &self.replay_ticks
+    }
+
+    // everything on the board that something is drawn on
+    pub fn get_positions(&self) -> Vec<BoardPos>{
+        self.tiles.iter().map(|tile| tile.get_pos())
+            .chain(self.blockobjects.iter().chain(self.activeblockobjects.iter()).flat_map(|bo| bo.block_locations()))
+            .collect()
+    }
+
+    // what to highlight when drawing the board in error mode
+    pub fn set_error(&mut self, sim_err: &SimulationError){
+        self.error_locs = sim_err.relevant_locations();
+        self.error_blockobjects = sim_err.block_objects();
+        self.split_diagnostics = sim_err.split_diagnostics().cloned();
+    }
+
+    // the positions of every active block object, in the same form replays use
+    pub fn get_active_blocks(&self) -> Vec<Vec<BoardPos>>{
+        self.activeblockobjects.iter().map(sorted_blocks).collect()
+    }
+
+    // set up the board from a saved solution, using the block objects from its level
+    pub fn load_solution(&mut self, solution: &SerializedSolution, level_blockobjects: &[BlockObject]) -> GameResult{
+        self.tiles.clear();
+        self.blockobjects.clear();
+
+        for placement in solution.blockobjects.iter(){
+            let mut blockobject = level_blockobjects.iter()
+                .find(|bo| bo.id == placement.id)
+                .ok_or_else(|| GameError::CustomError(format!("Level {} has no block object {}", solution.level, placement.id)))?
+                .clone();
+            // held block objects get rotated around the origin
+            for _ in 0..placement.quarter_turns.rem_euclid(4){
+                blockobject.rotate_cw(BoardPos{x: 0, y: 0});
+            }
+            self.place_blockobject(blockobject, placement.pos)?;
+        }
+
+        let push_levels = self.rules.priorities.get_push_levels();
+        for stile in solution.tiles.iter(){
+            // clamping would quietly give the tile a different priority than the one that was saved
+            if let Some(priority_level) = stile.priority_level.filter(|level| !(1..=push_levels).contains(level)){
+                return Err(GameError::CustomError(format!(
+                    "The tile at ({}, {}) has priority level {}, but level {} only has {}",
+                    stile.pos.x, stile.pos.y, priority_level, solution.level, push_levels
+                )));
+            }
+            let i = self.place_tile(stile.tiletype, stile.pos, stile.dir.unwrap_or(Direction::Right));
+            if let Some(number) = stile.number{
+                self.tiles[i].set_number(number);
+            }
+            if let Some(priority_level) = stile.priority_level{
+                self.tiles[i].set_priority_level(priority_level, push_levels);
+            }
+        }
+        Ok(())
+    }
+
+    // the opposite of load_solution
+    pub fn to_solution(&self, level: &str, level_blockobjects: &[BlockObject]) -> GameResult<SerializedSolution>{
+        let mut placements = vec![];
+        for bo in self.blockobjects.iter(){
+            let mut placed = bo.clone();
+            let pos = placed.get_top_left()?;
+            placed.translate(-pos.x, -pos.y);
+            let placed_blocks = sorted_blocks(&placed);
+
+            let mut original = level_blockobjects.iter()
+                .find(|levelbo| levelbo.id == bo.id)
+                .ok_or_else(|| GameError::CustomError(format!("Level {} has no block object {}", level, bo.id)))?
+                .clone();
+            // find how far it was turned, which is the same way load_solution turns it
+            let mut quarter_turns = None;
+            for turns in 0..4{
+                let tl = original.get_top_left()?;
+                original.translate(-tl.x, -tl.y);
+                if sorted_blocks(&original) == placed_blocks{
+                    quarter_turns = Some(turns);
+                    break;
+                }
+                original.rotate_cw(BoardPos{x: 0, y: 0});
+            }
+            let quarter_turns = quarter_turns
+                .ok_or_else(|| GameError::CustomError(format!("Block object {} doesn't match its level", bo.id)))?;
+            placements.push(SerializedPlacement{id: bo.id, pos, quarter_turns});
+        }
+
+        let tiles = self.tiles.iter().map(|tile| SerializedTile{
+            tiletype: tile.get_type(),
+            pos: tile.get_pos(),
+            dir: Some(tile.get_dir()),
+            number: if tile.get_type().numbered() {Some(tile.get_number())} else {None},
+            priority_level: if tile.get_priority_level() != 1 {Some(tile.get_priority_level())} else {None}
+        }).collect();
+
+        Ok(SerializedSolution{
+            level: level.to_string(),
+            blockobjects: placements,
+            tiles
+        })
+    }
+
     fn place_tile(&mut self, tiletype: TileType, pos: BoardPos, dir: Direction) -> usize{
         let newtile = Tile::new_directional(tiletype, pos, dir);
         let to_remove: Option<usize> = self.find_tile(pos);
@@ -360,6 +1187,39 @@ impl BoardState{
         }
     }
 
+    // a new tile of the same kind as a held one
+    fn place_tile_like(&mut self, tile: &Tile, pos: BoardPos, dir: Direction){
+        let i = self.place_tile(tile.get_type(), pos, dir);
+        self.tiles[i].set_priority_level(tile.get_priority_level(), self.rules.priorities.get_push_levels());
+    }
+
+    // put down a tile that already exists, replacing whatever was there
+    fn insert_tile(&mut self, tile: Tile){
+        match self.find_tile(tile.get_pos()){
+            Some(i) => self.tiles[i] = tile,
+            None => self.tiles.push(tile)
+        }
+    }
+
+    // copies of every tile in a rectangle, with positions relative to anchor
+    fn copy_tiles(&self, tl: BoardPos, br: BoardPos, anchor: BoardPos) -> Vec<Tile>{
+        let mut copied: Vec<Tile> = self.tiles.iter()
+            .filter(|tile| (tl.x..=br.x).contains(&tile.get_x()) && (tl.y..=br.y).contains(&tile.get_y()))
+            .cloned()
+            .collect();
+        for tile in copied.iter_mut(){
+            tile.apply(MovementType::Teleport{dx: -anchor.x, dy: -anchor.y});
+        }
+        copied
+    }
+
+    // like copy_tiles, but they get taken off the board
+    fn take_tiles(&mut self, tl: BoardPos, br: BoardPos, anchor: BoardPos) -> Vec<Tile>{
+        let taken = self.copy_tiles(tl, br, anchor);
+        self.tiles.retain(|tile| !((tl.x..=br.x).contains(&tile.get_x()) && (tl.y..=br.y).contains(&tile.get_y())));
+        taken
+    }
+
     fn rotate_tile_cw(&mut self, pos: BoardPos){
         if let Some(i) = self.find_tile(pos){
             self.tiles[i].rotate_cw();
@@ -379,6 +1239,53 @@ impl BoardState{
         }
     }
 
+    // does nothing if there is no numbered tile (gates and teleporters) at pos
+    fn set_tile_number(&mut self, pos: BoardPos, number: i32){
+        if let Some(i) = self.find_tile(pos){
+            if self.tiles[i].get_type().numbered(){
+                self.tiles[i].set_number(number);
+            }
+        }
+    }
+
+    fn numbered_tile_at(&mut self, pos: BoardPos) -> bool{
+        match self.find_tile(pos){
+            Some(i) => self.tiles[i].get_type().numbered(),
+            None => false
+        }
+    }
+
+    fn change_tile_number(&mut self, pos: BoardPos, delta: i32){
+        if let Some(i) = self.find_tile(pos){
+            let number = self.tiles[i].get_number() + delta;
+            self.set_tile_number(pos, number);
+        }
+    }
+
+    // how far something entering a teleporter on this channel moves
+    // this is the offset between the top left of the entrances and the top left of the exits
+    fn teleport_offset(&self, channel: i32) -> SimulationResult<(i32, i32)>{
+        let is_entrance = |tile: &&Tile| tile.movement() == TileMovement::Teleport{channel};
+        let is_exit = |tile: &&Tile| tile.exit_channel() == Some(channel);
+        let top_left = |positions: Vec<BoardPos>| {
+            positions.into_iter().reduce(|a, b| BoardPos{x: a.x.min(b.x), y: a.y.min(b.y)})
+        };
+        let entrances = top_left(self.tiles.iter().filter(is_entrance).map(|tile| tile.get_pos()).collect());
+        let exits = top_left(self.tiles.iter().filter(is_exit).map(|tile| tile.get_pos()).collect());
+
+        match (entrances, exits){
+            (Some(entrance), Some(exit)) => Ok((exit.x - entrance.x, exit.y - entrance.y)),
+            _ => Err(SimulationError::MissingTeleporterExit{
+                tick: self.game_ticks,
+                channel,
+                entrances: self.tiles.iter()
+                    .filter(is_entrance)
+                    .map(|tile| tile.get_pos())
+                    .collect()
+            })
+        }
+    }
+
     fn place_blockobject(&mut self, mut blockobject: BlockObject, pos: BoardPos) -> GameResult{
         let tl = blockobject.get_top_left()?;
         blockobject.translate(pos.x - tl.x, pos.y - tl.y);
@@ -401,34 +1308,212 @@ impl BoardState{
         Ok(())
     }
 
-    fn process_start(&mut self) -> GameResult{
+    pub fn process_start(&mut self) -> GameResult{
         // reset the counters
         for bo in self.blockobjects.iter_mut(){
             bo.counter = bo.start_counter;
         }
 
         for tile in self.tiles.iter_mut(){
-            tile.save_dir()
+            tile.save_dir();
+            tile.save_counter();
         }
 
         self.game_ticks = 0;
+        self.replay_ticks.clear();
 
         Ok(())
     }
 
-    fn process_end(&mut self) -> GameResult{
+    pub fn process_end(&mut self) -> GameResult{
         // remove active blockobjects
         self.activeblockobjects.clear();
 
         for tile in self.tiles.iter_mut(){
-            tile.load_dir()
+            tile.load_dir();
+            tile.load_counter();
         }
 
         Ok(())
     }
 
+    // record that block object i is at pos at some point during this step
+    // the second block object to get recorded at a position is a potential collision
+    fn mark_occupied(collision_map: &mut HashMap<BoardPos, Vec<usize>>, collisions: &mut Vec<BoardPos>, pos: BoardPos, i: usize){
+        if let Some(curr) = collision_map.get_mut(&pos){
+            if !curr.contains(&i){
+                if curr.len() == 1{ // this is the second thing inserted
+                    collisions.push(pos);
+                }
+                curr.push(i);
+            }
+        }else{
+            collision_map.insert(pos, vec![i]);
+        }
+    }
+
+    fn record(replay_ticks: &mut [Vec<ReplayEvent>], event: ReplayEvent){
+        if let Some(events) = replay_ticks.last_mut(){
+            events.push(event);
+        }
+    }
+
+    // try to split a block object that is pushed in several directions
+    // it gets cut along every straight line where blocks on either side are pushed different ways,
+    // so there can be several parallel seams or a cross of two
+    // every piece then has to be pushed in exactly one direction, pieces going the same way stay together,
+    // and the pieces on either side of a seam have to keep out of each other's way while they move apart
+    // returns the pieces and their directions, the first piece is the one holding the first block
+    // if it doesn't work, returns what went wrong so it can be shown to the player
+    fn split_pieces(bo: &BlockObject, tiles: &[Tile]) -> Result<(Vec<Vec<BoardPos>>, Vec<Direction>), SplitDiagnostics>{
+        let mut pushes: HashMap<BoardPos, Direction> = HashMap::new();
+        for tile in tiles.iter(){
+            if let TileMovement::Push(dir) = tile.movement(){
+                pushes.insert(tile.get_pos(), dir);
+            }
+        }
+        let locations: HashSet<BoardPos> = bo.block_locations().into_iter().collect();
+        let mut diagnostics = SplitDiagnostics::default();
+
+        // every pair of blocks a seam runs between, and which way it is from the first to the second
+        let mut crossings: Vec<(BoardPos, BoardPos, Direction)> = vec![];
+        let mut vert_seams: HashSet<i32> = HashSet::new();
+        let mut hori_seams: HashSet<i32> = HashSet::new();
+        for (vertical, a_to_b) in [(true, Direction::Right), (false, Direction::Down)]{
+            let (dx, dy) = a_to_b.to_offset();
+            let lines: HashSet<i32> = locations.iter().map(|pos| pos.x*dx + pos.y*dy).collect();
+            for line in lines{
+                let line_crossings: Vec<(BoardPos, BoardPos)> = locations.iter()
+                    .filter(|a| (a.x*dx + a.y*dy) == line && locations.contains(&BoardPos{x: a.x+dx, y: a.y+dy}))
+                    .map(|a| (*a, BoardPos{x: a.x+dx, y: a.y+dy}))
+                    .collect();
+                let pushed_apart = line_crossings.iter().any(|(a, b)| {
+                    matches!((pushes.get(a), pushes.get(b)), (Some(a_dir), Some(b_dir)) if a_dir != b_dir)
+                });
+                if !pushed_apart{
+                    continue;
+                }
+
+                let along: Vec<i32> = line_crossings.iter().map(|(a, _)| a.x*dy + a.y*dx).collect();
+                diagnostics.seams.push(Seam{
+                    vertical,
+                    line,
+                    start: *along.iter().min().unwrap_or(&0),
+                    end: *along.iter().max().unwrap_or(&0)
+                });
+                if vertical {vert_seams.insert(line);} else {hori_seams.insert(line);}
+                crossings.extend(line_crossings.into_iter().map(|(a, b)| (a, b, a_to_b)));
+            }
+        }
+        if vert_seams.is_empty() && hori_seams.is_empty(){
+            return Err(diagnostics);
+        }
+
+        let pieces = bo.seam_pieces(&vert_seams, &hori_seams);
+        let mut dirs = vec![];
+        for piece in pieces.iter(){
+            let mut counts: HashMap<Direction, usize> = HashMap::new();
+            for pos in piece.iter(){
+                if let Some(dir) = pushes.get(pos){
+                    *counts.entry(*dir).or_insert(0) += 1;
+                }
+            }
+            // the most common direction is the way the piece should go, anything else is on the wrong side
+            // ties go to whichever comes first clockwise from right, so the overlay doesn't flicker between runs
+            let most_common = [Direction::Right, Direction::Down, Direction::Left, Direction::Up].into_iter()
+                .filter(|dir| counts.contains_key(dir))
+                .rev()
+                .max_by_key(|dir| counts[dir]);
+            match most_common{
+                Some(dir) => {
+                    diagnostics.wrong_side.extend(piece.iter().filter(|pos| pushes.get(pos).is_some_and(|d| *d != dir)));
+                    dirs.push(dir);
+                },
+                None => return Err(diagnostics) // a piece with nothing pushing it
+            }
+        }
+        if !diagnostics.wrong_side.is_empty(){
+            return Err(diagnostics);
+        }
+
+        // pieces going the same way across a seam are joined back up, the lowest piece number stands for the group
+        // so the first piece still holds the first block
+        let piece_of: HashMap<BoardPos, usize> = pieces.iter().enumerate()
+            .flat_map(|(p, piece)| piece.iter().map(move |pos| (*pos, p)))
+            .collect();
+        let mut joined: Vec<usize> = (0..pieces.len()).collect();
+        let root = |joined: &[usize], mut p: usize| {
+            while joined[p] != p{
+                p = joined[p];
+            }
+            p
+        };
+        for (a, b, _) in crossings.iter(){
+            let (ra, rb) = (root(&joined, piece_of[a]), root(&joined, piece_of[b]));
+            if ra != rb && dirs[ra] == dirs[rb]{
+                joined[ra.max(rb)] = ra.min(rb);
+            }
+        }
+        let roots: Vec<usize> = (0..pieces.len()).filter(|p| root(&joined, *p) == *p).collect();
+        let groups: Vec<Vec<BoardPos>> = roots.iter()
+            .map(|r| (0..pieces.len()).filter(|p| root(&joined, *p) == *r).flat_map(|p| pieces[p].iter().copied()).collect())
+            .collect();
+
+        // the same rule as collisions between block objects, nothing can be where the other piece is or will be
+        let footprints: Vec<HashSet<BoardPos>> = groups.iter().zip(roots.iter()).map(|(group, r)| {
+            let (dx, dy) = dirs[*r].to_offset();
+            group.iter().flat_map(|pos| [*pos, BoardPos{x: pos.x+dx, y: pos.y+dy}]).collect()
+        }).collect();
+        let group_of = |pos: &BoardPos| roots.iter().position(|r| *r == root(&joined, piece_of[pos])).unwrap_or(0);
+        for (a, b, a_to_b) in crossings.iter(){
+            let (ga, gb) = (group_of(a), group_of(b));
+            if ga != gb && !footprints[ga].is_disjoint(&footprints[gb]){
+                diagnostics.clashing.push((*a, a_to_b.clockwise().clockwise()));
+                diagnostics.clashing.push((*b, *a_to_b));
+            }
+        }
+        if !diagnostics.clashing.is_empty(){
+            return Err(diagnostics);
+        }
+        let group_dirs = roots.iter().map(|r| dirs[*r]).collect();
+        Ok((groups, group_dirs))
+    }
+
+    // add up the pushes from every push tile, the direction with the most tiles behind it wins
+    // if there is a tie, returns how many tiles pushed in each direction
+    fn pushing_power(tiles: &[Tile]) -> Result<Direction, Vec<(Direction, i32)>>{
+        let mut counts: HashMap<Direction, i32> = HashMap::new();
+        for tile in tiles.iter(){
+            if let TileMovement::Push(dir) = tile.movement(){
+                *counts.entry(dir).or_insert(0) += 1;
+            }
+        }
+        let count = |dir: Direction| *counts.get(&dir).unwrap_or(&0);
+        let sum_x = count(Direction::Right) - count(Direction::Left);
+        let sum_y = count(Direction::Down) - count(Direction::Up);
+
+        if sum_x.abs() > sum_y.abs(){
+            Ok(if sum_x > 0 {Direction::Right} else {Direction::Left})
+        }else if sum_y.abs() > sum_x.abs(){
+            Ok(if sum_y > 0 {Direction::Down} else {Direction::Up})
+        }else{
+            Err([Direction::Right, Direction::Down, Direction::Left, Direction::Up].into_iter()
+                .filter(|dir| count(*dir) > 0)
+                .map(|dir| (dir, count(dir)))
+                .collect())
+        }
+    }
+
+    // the corners of the smallest rectangle holding every tile and every input and output
+    fn playable_area(&self) -> (BoardPos, BoardPos){
+        let positions: Vec<BoardPos> = self.tiles.iter().map(|tile| tile.get_pos())
+            .chain(self.blockobjects.iter().flat_map(|bo| bo.block_locations()))
+            .collect();
+        BoardCanvas::bounds(&positions, 0)
+    }
+
     // returning true means we won
-    fn process_step(&mut self) -> SimulationResult<bool>{
+    pub fn process_step(&mut self) -> SimulationResult<bool>{
         // did we win?
         let mut winning = true;
         for out in self.blockobjects.iter().filter(|bo| bo.mode == BlockObjectMode::Output){
@@ -439,39 +1524,90 @@ impl BoardState{
         if winning{
             return Ok(true);
         }
+        self.replay_ticks.push(vec![]);
+
+        // if nothing changes in a tick, and nothing is waiting to change, nothing ever will again
+        let mut changed = self.activeblockobjects.iter().any(|bo| bo.just_moved); // delay tiles may still move them
+        let tiles_before: Vec<(Direction, i32)> = self.tiles.iter().map(|tile| (tile.get_dir(), tile.get_counter())).collect();
 
         // place block objects every other tick
         if self.game_ticks % 2 == 0{
             for bo in self.blockobjects.iter_mut(){
                 if bo.mode == BlockObjectMode::Input && bo.counter > 0{
+                    changed = true;
                     let mut bocopy = bo.clone();
 
                     bocopy.mode = BlockObjectMode::Processing;
                     bocopy.just_moved = true; // make delay blocks work more intuitively
                     bo.counter -= 1;
 
+                    Self::record(&mut self.replay_ticks, ReplayEvent::Spawn{input: bo.id, blocks: sorted_blocks(&bocopy)});
                     self.activeblockobjects.push(bocopy);
                 }
             }
         }
 
+        // let tiles react to anything that was just pushed onto them (duplicators make copies)
+        let mut spawned: Vec<(usize, BlockObject)> = vec![];
+        for (i, bo) in self.activeblockobjects.iter_mut().enumerate(){
+            let last_move = bo.last_move;
+            if last_move == MovementType::None{
+                continue;
+            }
+            for tile in self.tiles.iter(){
+                let pos = tile.get_pos();
+                // pos was covered before the move if whatever was there ended up somewhere still covered
+                if bo.overlap_tile(pos) && !bo.overlap_tile(last_move.apply(pos)){
+                    if let Some(newbo) = tile.on_arrive(bo)?{
+                        spawned.push((i, newbo));
+                    }
+                }
+            }
+        }
+        for (from, bocopy) in spawned.into_iter(){
+            changed = true;
+            let mut block_objects = vec![];
+            let mut locations = vec![];
+            for (j, other) in self.activeblockobjects.iter_mut().enumerate(){
+                let overlap: Vec<BoardPos> = bocopy.block_locations().into_iter()
+                    .filter(|pos| other.overlap_tile(*pos))
+                    .collect();
+                if !overlap.is_empty(){
+                    block_objects.push(j);
+                    locations.extend(overlap);
+                }
+            }
+            // the copy goes on the board even if it collided, so it shows up with the error
+            Self::record(&mut self.replay_ticks, ReplayEvent::Duplicate{from, blocks: sorted_blocks(&bocopy)});
+            self.activeblockobjects.push(bocopy);
+            if !block_objects.is_empty(){
+                block_objects.push(self.activeblockobjects.len() - 1);
+                return Err(SimulationError::Collision{
+                    tick: self.game_ticks,
+                    block_objects,
+                    locations
+                });
+            }
+        }
+
 
         let n = self.activeblockobjects.len();
-        let mut max_priority = vec![0; n];
+        let mut max_priority: Vec<i32> = vec![0; n];
         let mut relevant_tiles: Vec<Vec<Tile>> = vec![vec![]; n];
 
         // can I make this more efficient?
         for tile in self.tiles.iter(){
             for (i, blockobject) in self.activeblockobjects.iter_mut().enumerate(){
                 if blockobject.overlap_tile(tile.get_pos()){
-                    if tile.get_type() == TileType::DelayTile && blockobject.just_moved{
+                    if tile.ignores_just_moved() && blockobject.just_moved{
                         continue
                     }
-                    if tile.get_type().get_priority() > max_priority[i]{
-                        max_priority[i] = tile.get_type().get_priority();
+                    let priority = self.rules.priorities.get_priority(tile);
+                    if priority > max_priority[i]{
+                        max_priority[i] = priority;
                         relevant_tiles[i].clear();
                     }
-                    if tile.get_type().get_priority() == max_priority[i]{
+                    if priority == max_priority[i]{
                         relevant_tiles[i].push(tile.clone());
                     }
                 }
@@ -480,27 +1616,32 @@ impl BoardState{
 
         let mut moves: Vec<MovementType> = vec![MovementType::None; n];
 
-        // make sure we only have one rot tile
-        // assume that rot tiles have their own reserved priority (1 for now)
+        // make sure we only have one rot or mirror tile
+        // assume that rot and mirror tiles have their own reserved priority (1 for now)
         for i in 0..self.activeblockobjects.len(){
-            if let Some(tile) = relevant_tiles[i].get(0){
-                if tile.get_type().is_rot_tile(){
+            if let Some(tile) = relevant_tiles[i].first(){
+                let transform = match tile.movement(){
+                    TileMovement::Rotate{quarter_turns} => Some(MovementType::Rotation{quarter_turns, around: tile.get_pos()}),
+                    TileMovement::Mirror{vertical} => Some(MovementType::Mirror{vertical, around: tile.get_pos()}),
+                    _ => None
+                };
+                if let Some(movement) = transform{
                     if relevant_tiles[i].len() > 1{
-                        return Err(SimulationError{
-                            message: "Attempted to rotate block from multiple pivots".to_string(),
-                            relevant_locations: relevant_tiles[i].iter().map(|tile| tile.get_pos()).collect()
-                        });
-                    } else {
-                        match tile.get_type(){
-                            TileType::RotTileCW => {
-                                moves[i] = MovementType::Rotation{cw: true, around: tile.get_pos()}
-                            },
-                            TileType::RotTileCCW => {
-                                moves[i] = MovementType::Rotation{cw: false, around: tile.get_pos()}
+                        let locations = relevant_tiles[i].iter().map(|tile| tile.get_pos()).collect();
+                        return Err(match movement{
+                            MovementType::Rotation{..} => SimulationError::MultiPivotRotation{
+                                tick: self.game_ticks,
+                                block_object: i,
+                                pivots: locations
                             },
-                            _ => panic!("Unregistered Rotation tile type")
-                        }
+                            _ => SimulationError::MultiAxisMirror{
+                                tick: self.game_ticks,
+                                block_object: i,
+                                axes: locations
+                            }
+                        });
                     }
+                    moves[i] = movement;
                 }
             }
         }
@@ -509,8 +1650,8 @@ impl BoardState{
         // list all directions
         for i in 0..self.activeblockobjects.len(){
             for tile in relevant_tiles[i].iter(){
-                if tile.get_type().is_push_tile(){
-                    move_dirs[i].insert(tile.get_dir());
+                if let TileMovement::Push(dir) = tile.movement(){
+                    move_dirs[i].insert(dir);
                 }
             }
         }
@@ -522,94 +1663,128 @@ impl BoardState{
                 moves[i] = MovementType::Translation(*temp[0]);
             } else if move_dirs[i].len() > 1{
                 let mut good = false;
-                if relevant_tiles[i][0].get_dir() == Direction::Left || relevant_tiles[i][0].get_dir() == Direction::Right{
-                    good = true;
-                    let seam = match relevant_tiles[i][0].get_dir(){
-                        Direction::Left => relevant_tiles[i][0].get_pos().x,
-                        Direction::Right => relevant_tiles[i][0].get_pos().x-1,
-                        _default => panic!("this should be impossible")
-                    };
-
-                    // are all tiles on the correct side of the seam?
-                    for tile in relevant_tiles[i].iter(){
-                        match tile.get_dir(){
-                            Direction::Left => {good = good && (tile.get_pos().x <= seam)}
-                            Direction::Right => {good = good && (tile.get_pos().x > seam)}
-                            _default => {good = false;}
+                let mut split_diagnostics = None;
+                match Self::split_pieces(&self.activeblockobjects[i], &relevant_tiles[i]){
+                    Ok((pieces, dirs)) => {
+                        moves[i] = MovementType::Translation(dirs[0]);
+                        for (piece, dir) in pieces.iter().zip(dirs).skip(1){
+                            let new_bo = self.activeblockobjects[i].split_off(piece);
+                            Self::record(&mut self.replay_ticks, ReplayEvent::Split{from: i, blocks: sorted_blocks(&new_bo)});
+                            self.activeblockobjects.push(new_bo);
+                            moves.push(MovementType::Translation(dir));
                         }
-                    }
-                    if good{
-                        // do we have the whole seam covered
-                        let mut sides_covered = HashMap::new();
-                        for y in self.activeblockobjects[i].get_vert_seam(seam){
-                            sides_covered.insert(y, (false, false));
+                        good = true;
+                    },
+                    Err(diagnostics) => {
+                        if !diagnostics.seams.is_empty(){
+                            split_diagnostics = Some(Box::new(diagnostics));
                         }
-                        for tile in relevant_tiles[i].iter(){
-                            if tile.get_pos().x == seam{
-                                sides_covered.get_mut(&tile.get_pos().y).map(|p| p.0 = true);
-                            } else if tile.get_pos().x == seam + 1{
-                                sides_covered.get_mut(&tile.get_pos().y).map(|p| p.1 = true);
-                            }
-                        }
-                        for (_k, v) in sides_covered{
-                            if v != (true, true){
-                                good = false;
-                            }
-                        }
-                    }
-                    if good{
-                        let new_bo = self.activeblockobjects[i].split_vert_seam(seam);
-                        self.activeblockobjects.push(new_bo);
-                        moves[i] = MovementType::Translation(Direction::Left);
-                        moves.push(MovementType::Translation(Direction::Right));
                     }
-                } else if relevant_tiles[i][0].get_dir() == Direction::Up || relevant_tiles[i][0].get_dir() == Direction::Down{
-                    good = true;
-                    let seam = match relevant_tiles[i][0].get_dir(){
-                        Direction::Up => relevant_tiles[i][0].get_pos().y,
-                        Direction::Down => relevant_tiles[i][0].get_pos().y-1,
-                        _default => panic!("this should be impossible")
-                    };
-
-                    // are all tiles on the correct side of the seam?
-                    for tile in relevant_tiles[i].iter(){
-                        match tile.get_dir(){
-                            Direction::Up => {good = good && (tile.get_pos().y <= seam)}
-                            Direction::Down => {good = good && (tile.get_pos().y > seam)}
-                            _default => {good = false;}
+                }
+                if !good && self.rules.push_resolution == PushResolution::PushingPower{
+                    match Self::pushing_power(&relevant_tiles[i]){
+                        Ok(dir) => {
+                            moves[i] = MovementType::Translation(dir);
+                            good = true;
+                        },
+                        Err(counts) => {
+                            return Err(SimulationError::ConflictingPush{
+                                tick: self.game_ticks,
+                                block_object: i,
+                                tiles: relevant_tiles[i].iter().map(|tile| tile.get_pos()).collect(),
+                                pushing_power: Some(counts),
+                                split_diagnostics
+                            });
                         }
                     }
-                    if good{
-                        // do we have the whole seam covered
-                        let mut sides_covered = HashMap::new();
-                        for x in self.activeblockobjects[i].get_hori_seam(seam){
-                            sides_covered.insert(x, (false, false));
-                        }
-                        for tile in relevant_tiles[i].iter(){
-                            if tile.get_pos().y == seam{
-                                sides_covered.get_mut(&tile.get_pos().x).map(|p| p.0 = true);
-                            } else if tile.get_pos().y == seam + 1{
-                                sides_covered.get_mut(&tile.get_pos().x).map(|p| p.1 = true);
-                            }
-                        }
-                        for (_k, v) in sides_covered{
-                            if v != (true, true){
-                                good = false;
-                            }
+                }
+                if !good{
+                    return Err(SimulationError::ConflictingPush{
+                        tick: self.game_ticks,
+                        block_object: i,
+                        tiles: relevant_tiles[i].iter().map(|tile| tile.get_pos()).collect(),
+                        pushing_power: None,
+                        split_diagnostics
+                    });
+                }
+            }
+        }
+
+        // teleport anything resting entirely on entrances of one channel
+        for (i, bo) in self.activeblockobjects.iter().enumerate(){
+            let mut channels = HashSet::new();
+            let mut on_entrances = true;
+            for pos in bo.block_locations(){
+                let entrance = self.tiles.iter().find(|tile| tile.get_pos() == pos).map(|tile| tile.movement());
+                match entrance{
+                    Some(TileMovement::Teleport{channel}) => {channels.insert(channel);},
+                    _ => {on_entrances = false;}
+                }
+            }
+            if on_entrances && channels.len() == 1{
+                let channel = *channels.iter().next().expect("channels has one element");
+                let (dx, dy) = self.teleport_offset(channel)?;
+                moves[i] = MovementType::Teleport{dx, dy};
+            }
+        }
+
+        // tiles can stop things from moving onto or over them (gate tiles let every nth one through)
+        // each block object counts once per gate, a block object waiting at a gate doesn't count again
+        // nothing is counted until the moves have gone through, so arrivals keeps them until then
+        let mut gate_counters: HashMap<usize, i32> = HashMap::new();
+        let mut arrivals: Vec<Vec<(usize, BoardPos, bool)>> = vec![vec![]; self.activeblockobjects.len()];
+        for (i, bo) in self.activeblockobjects.iter_mut().enumerate(){
+            if let MovementType::Translation(move_dir) = moves[i]{
+                let (dx, dy) = move_dir.to_offset();
+                let mut blocked = false;
+                for (t, tile) in self.tiles.iter().enumerate(){
+                    let tile_pos = tile.get_pos();
+                    let entering = !bo.overlap_tile(tile_pos) &&
+                        bo.overlap_tile(BoardPos{x: tile_pos.x - dx, y: tile_pos.y - dy});
+                    if !entering{
+                        continue;
+                    }
+                    match bo.gate_arrivals.get(&tile_pos){
+                        Some(true) => (),
+                        Some(false) => blocked = true,
+                        None => {
+                            let counter = *gate_counters.get(&t).unwrap_or(&tile.get_counter());
+                            let let_through = tile.lets_through(counter + 1);
+                            blocked |= !let_through;
+                            arrivals[i].push((t, tile_pos, let_through));
                         }
                     }
-                    if good{
-                        let new_bo = self.activeblockobjects[i].split_hori_seam(seam);
-                        self.activeblockobjects.push(new_bo);
-                        moves[i] = MovementType::Translation(Direction::Up);
-                        moves.push(MovementType::Translation(Direction::Down));
+                }
+                // later block objects this tick see the counters as if this one's move went through
+                for (t, _, let_through) in arrivals[i].iter(){
+                    let counter = *gate_counters.get(t).unwrap_or(&self.tiles[*t].get_counter());
+                    if !blocked{
+                        gate_counters.insert(*t, 0);
+                    }else if !let_through{
+                        gate_counters.insert(*t, counter + 1);
                     }
                 }
-                if !good{
-                    return Err(SimulationError{
-                        message: "Attempted to move block in multiple directions".to_string(),
-                        relevant_locations: relevant_tiles[i].iter().map(|tile| tile.get_pos()).collect()
-                    });
+                if blocked{
+                    moves[i] = MovementType::None;
+                }
+            }
+
+            // rotating and mirroring over a tile that would stop the next block object is blocked the same way
+            // sweeps never count as arrivals, the block object is only passing over
+            let sweep = match moves[i]{
+                MovementType::Rotation{quarter_turns, around} => bo.rotation_sweep(quarter_turns, around),
+                MovementType::Mirror{vertical, around} => bo.mirror_sweep(vertical, around),
+                _ => vec![]
+            };
+            for pos in sweep{
+                if bo.overlap_tile(pos){
+                    continue;
+                }
+                for (t, tile) in self.tiles.iter().enumerate(){
+                    let counter = *gate_counters.get(&t).unwrap_or(&tile.get_counter());
+                    if tile.get_pos() == pos && !tile.lets_through(counter + 1){
+                        moves[i] = MovementType::None;
+                    }
                 }
             }
         }
@@ -624,10 +1799,10 @@ impl BoardState{
         let mut merge_groups: Vec<HashSet<usize>> = Vec::new();
         for i in 0..self.activeblockobjects.len(){
             for j in 0..self.activeblockobjects.len(){
-                let itl = self.activeblockobjects[i].get_top_left().map_err(|e| SimulationError::from_string(e.to_string()))?;
-                let ibr = self.activeblockobjects[i].get_bottom_right().map_err(|e| SimulationError::from_string(e.to_string()))?;
-                let jtl = self.activeblockobjects[j].get_top_left().map_err(|e| SimulationError::from_string(e.to_string()))?;
-                let jbr = self.activeblockobjects[j].get_bottom_right().map_err(|e| SimulationError::from_string(e.to_string()))?;
+                let itl = self.activeblockobjects[i].get_top_left()?;
+                let ibr = self.activeblockobjects[i].get_bottom_right()?;
+                let jtl = self.activeblockobjects[j].get_top_left()?;
+                let jbr = self.activeblockobjects[j].get_bottom_right()?;
 
                 let can_merge = match (moves[i], moves[j]){
                     (MovementType::Translation(Direction::Up), MovementType::Translation(Direction::Down)) =>
@@ -660,6 +1835,15 @@ impl BoardState{
         }
 
         // resolve merges
+        if !merge_groups.is_empty(){
+            changed = true;
+            let groups = merge_groups.iter().map(|group| {
+                let mut group: Vec<usize> = group.iter().copied().collect();
+                group.sort_unstable();
+                group
+            }).collect();
+            Self::record(&mut self.replay_ticks, ReplayEvent::Merge{groups});
+        }
         let mut to_remove: Vec<usize> = vec![];
         for group in merge_groups.into_iter(){
             let mut merged_group = BlockObject::new();
@@ -669,11 +1853,13 @@ impl BoardState{
             }
             self.activeblockobjects.push(merged_group);
             moves.push(MovementType::None);
+            arrivals.push(vec![]);
         }
         to_remove.sort_unstable_by_key(|i| -(*i as i64));
         for i in to_remove{
             self.activeblockobjects.remove(i);
             moves.remove(i);
+            arrivals.remove(i);
         }
 
         let mut collision_map: HashMap<BoardPos, Vec<usize>> = HashMap::new();
@@ -683,20 +1869,25 @@ impl BoardState{
         for i in 0..self.activeblockobjects.len(){
             let bo = &mut self.activeblockobjects[i];
             // before move check
-            for block in bo.blocks.iter_mut(){
-                if let Some(curr) = collision_map.get_mut(&block.pos){
-                    if let None = curr.iter().position(|a| *a == i){ // if i not in curr
-                        if curr.len() == 1{ // this is the second thing inserted
-                            collisions.push(block.pos.clone());
-                        }
-                        curr.push(i);
-                    }
-                }else{
-                    collision_map.insert(block.pos.clone(), vec![i]);
-                }
+            for block in bo.blocks.iter(){
+                Self::mark_occupied(&mut collision_map, &mut collisions, block.pos, i);
+            }
+
+            // rotating and mirroring blocks pass over everything between where they start and where they end
+            let sweep = match moves[i]{
+                MovementType::Rotation{quarter_turns, around} => bo.rotation_sweep(quarter_turns, around),
+                MovementType::Mirror{vertical, around} => bo.mirror_sweep(vertical, around),
+                _ => vec![]
+            };
+            for pos in sweep{
+                Self::mark_occupied(&mut collision_map, &mut collisions, pos, i);
             }
 
             // move
+            bo.last_move = moves[i];
+            if moves[i] != MovementType::None{
+                Self::record(&mut self.replay_ticks, ReplayEvent::Move{index: i, movement: moves[i]});
+            }
             let dx: i32;
             let dy: i32;
             if let MovementType::Translation(move_dir) = moves[i]{
@@ -712,30 +1903,23 @@ impl BoardState{
 
                 // set just_moved
                 bo.just_moved = true;
-            } else if let MovementType::Rotation{cw, around} = moves[i]{
-                if cw {
-                    bo.rotate_cw(around);
-                    bo.anim = BlockObjectAnimation::Rotation {theta: -PI/2.0, around};
-                }else{
-                    bo.rotate_ccw(around);
-                    bo.anim = BlockObjectAnimation::Rotation {theta: PI/2.0, around};
-                }
+            } else if let MovementType::Rotation{quarter_turns, around} = moves[i]{
+                bo.rotate(quarter_turns, around);
+                bo.anim = BlockObjectAnimation::Rotation {theta: -(quarter_turns as f32) * PI/2.0, around};
+            } else if let MovementType::Mirror{vertical, around} = moves[i]{
+                bo.mirror(vertical, around);
+                bo.anim = BlockObjectAnimation::Mirror {vertical, around};
+            } else if let MovementType::Teleport{dx, dy} = moves[i]{
+                bo.translate(dx, dy);
+                bo.anim = BlockObjectAnimation::Teleport {x: dx as f32, y: dy as f32};
+                bo.just_moved = true;
             } else {
                 bo.anim = BlockObjectAnimation::Translation {x: 0.0, y: 0.0};
             }
 
             // after move check
-            for block in bo.blocks.iter_mut(){
-                if let Some(curr) = collision_map.get_mut(&block.pos){
-                    if curr.iter().position(|a| *a == i) == None{
-                        if curr.len() == 1{ // this is the second thing inserted
-                            collisions.push(block.pos.clone());
-                        }
-                        curr.push(i);
-                    }
-                }else{
-                    collision_map.insert(block.pos.clone(), vec![i]);
-                }
+            for block in bo.blocks.iter(){
+                Self::mark_occupied(&mut collision_map, &mut collisions, block.pos, i);
             }
         }
 
@@ -758,24 +1942,47 @@ impl BoardState{
         }).collect::<Vec<_>>();
 
         if filtered_collisions.len() != 0{
-            return Err(SimulationError{
-                message: "Collision occured".to_string(),
-                relevant_locations: filtered_collisions
+            let mut block_objects: Vec<usize> = filtered_collisions.iter()
+                .flat_map(|collision| collision_map[collision].iter().copied())
+                .collect();
+            block_objects.sort_unstable();
+            block_objects.dedup();
+            return Err(SimulationError::Collision{
+                tick: self.game_ticks,
+                block_objects,
+                locations: filtered_collisions
             })
         }
 
-        // update alternating tiles
+        // count gate arrivals now that the moves are final
+        // anything that moved isn't waiting at a gate anymore
+        for (i, bo) in self.activeblockobjects.iter_mut().enumerate(){
+            let moved = moves[i] != MovementType::None;
+            if moved{
+                bo.gate_arrivals.retain(|_, let_through| *let_through);
+            }
+            for (t, tile_pos, let_through) in arrivals[i].iter().copied(){
+                let tile = &mut self.tiles[t];
+                if moved{
+                    tile.set_counter(0);
+                    bo.gate_arrivals.insert(tile_pos, true);
+                }else if !let_through{
+                    tile.set_counter(tile.get_counter() + 1);
+                    bo.gate_arrivals.insert(tile_pos, false);
+                }
+            }
+        }
+
+        // update tiles that were used (alternating tiles flip)
         // relevant_tiles contains copies of the real tiles, so I've got to grab the
         // real ones back
-        let mut toflip: HashSet<BoardPos> = HashSet::new();
+        let mut used: HashSet<BoardPos> = HashSet::new();
         for tile in relevant_tiles.iter().flatten(){
-            if tile.get_type() == TileType::AltTile{
-                toflip.insert(tile.get_pos());
-            }
+            used.insert(tile.get_pos());
         }
         for tile in self.tiles.iter_mut(){
-            if toflip.contains(&tile.get_pos()){
-                tile.flip_dir();
+            if used.contains(&tile.get_pos()){
+                tile.after_use();
             }
         }
 
@@ -790,22 +1997,56 @@ impl BoardState{
                     out.anim = BlockObjectAnimation::Output;
                     out.counter -= 1;
                     if out.counter < 0{
-                        return Err(SimulationError{
-                            message: format!("Too many objects in one output (expected {})", out.start_counter),
-                            relevant_locations: out.block_locations()
+                        return Err(SimulationError::OutputOverflow{
+                            tick: self.game_ticks,
+                            expected: out.start_counter,
+                            locations: out.block_locations()
                         })
                     }
+                    Self::record(&mut self.replay_ticks, ReplayEvent::Output{index: i, output: out.id});
                     to_remove.push(i);
                 }
             }
         }
+        changed |= !to_remove.is_empty();
         to_remove.sort_unstable_by_key(|i| -(*i as i64));
         for i in to_remove{
             self.activeblockobjects.remove(i);
             moves.remove(i); // not really neccisary, but the housekeeping is nice
         }
 
+        // the board goes on forever, but nothing past every tile and placed block object can ever come back
+        let (tl, br) = self.playable_area();
+        let outside = |pos: &BoardPos| pos.x < tl.x || pos.x > br.x || pos.y < tl.y || pos.y > br.y;
+        let gone: Vec<usize> = (0..self.activeblockobjects.len())
+            .filter(|i| self.activeblockobjects[*i].block_locations().iter().all(outside))
+            .collect();
+        if !gone.is_empty(){
+            return Err(SimulationError::OutOfBounds{
+                tick: self.game_ticks,
+                locations: gone.iter().flat_map(|i| self.activeblockobjects[*i].block_locations()).collect(),
+                block_objects: gone
+            });
+        }
+
+        changed |= moves.iter().any(|movement| *movement != MovementType::None);
+        let tiles_after: Vec<(Direction, i32)> = self.tiles.iter().map(|tile| (tile.get_dir(), tile.get_counter())).collect();
+        let inputs_left = self.blockobjects.iter().any(|bo| bo.mode == BlockObjectMode::Input && bo.counter > 0);
+        if !changed && !inputs_left && tiles_before == tiles_after{
+            return Err(SimulationError::Stalled{
+                tick: self.game_ticks,
+                block_objects: (0..self.activeblockobjects.len()).collect(),
+                locations: self.activeblockobjects.iter().flat_map(|bo| bo.block_locations()).collect()
+            });
+        }
+
         self.game_ticks += 1;
         Ok(false)
     }
 }
+
+impl Default for BoardState{
+    fn default() -> BoardState{
+        BoardState::new()
+    }
+}
@@ -1,45 +1,86 @@
-use ggez::graphics::{Color, Rect};
-
-use super::tile::TileType;
+use ggez::graphics::Color;
 
 // window and other setup
-pub const BOARD_POS: Rect = Rect::new(640.0,0.0,1280.0,1280.0);
-pub const SIDEBAR_POS: Rect = Rect::new(0.0,0.0,640.0,1280.0);
+pub const SIDEBAR_PROPORTION: f32 = 1.0/3.0; // of the screen width, the board gets the rest
+
+// files
+pub const REPLAY_DIR: &str = "replays"; // every run gets recorded here
+pub const REPLAY_MAX_SAVED: usize = 100; // older replays get deleted
+pub const EXPORT_DIR: &str = "exports";
+pub const SNAPSHOT_DIR: &str = "snapshots";
 
 // graphics
 pub const TILESIZE: f32 = 100.0;
+pub const ZOOM_LEVELS: [f32; 7] = [25.0, 35.0, 50.0, 70.0, 100.0, 140.0, 200.0]; // tile sizes the board can zoom between, TILESIZE should be one of them
 pub const HELD_TILESIZE: f32 = 100.0;
+pub const SEAM_WIDTH: f32 = 0.1; // relative to the tile size
+pub const CLASHING_WIDTH: f32 = 0.25; // relative to the tile size
 pub const BLOCK_ROUNDNESS: f32 = 0.3; // 0.0 is a square, 0.5 is a circle
 pub const OUTPUT_OUTLINE_WIDTH: f32 = 10.0;
 pub const SIDEBAR_TILESIZE: f32 = 100.0;
+pub const SIDEBAR_MIN_TILESIZE: f32 = 20.0; // tiles shrink down to this when the sidebar is narrow
 pub const SIDEBAR_SPACING_X: f32 = 50.0;
 pub const SIDEBAR_SPACING_Y: f32 = 50.0;
 pub const SIDEBAR_MARGING_X: f32 = 50.0;
 pub const SIDEBAR_MARGIN_Y: f32 = 50.0;
 pub const SIDEBAR_COUNTER_CIRCLE_RAD: f32 = 35.0;
+pub const SIDEBAR_SCROLLBAR_WIDTH: f32 = 12.0;
+pub const SIDEBAR_SCROLLBAR_INSET: f32 = 8.0; // from the edges of the sidebar, this should stay inside the margin
+pub const SIDEBAR_SCROLLBAR_MIN_LENGTH: f32 = 40.0;
 pub const POPUP_WIDTH: f32 = 800.0;
 pub const POPUP_HEIGHT: f32 = 600.0;
 pub const POPUP_MARGIN_X: f32 = 50.0;
 pub const POPUP_MARGIN_Y: f32 = 50.0;
 pub const POPUP_CORNER_RAD: f32 = 20.0;
+pub const MINIMAP_SIZE: f32 = 240.0;
+pub const MINIMAP_MARGIN: f32 = 20.0; // from the corner of the board
+pub const MINIMAP_BOARD_MARGIN: i32 = 2; // empty tiles around everything on the minimap
+pub const MINIMAP_VIEW_WIDTH: f32 = 2.0;
+pub const FRAME_MARGIN: i32 = 1; // empty tiles around everything when fitting it on screen
+pub const SELECTION_OUTLINE_WIDTH: f32 = 3.0;
 
 // text
 pub const SIDEBAR_COUNTER_SCALE: f32 = 48.0;
 pub const POPUP_FONT: &str = "LiberationMono-Regular";
 pub const POPUP_SCALE: f32 = 48.0;
+pub const SPLIT_DIAGNOSTICS_LEGEND: &str = "\nBlue lines are seams it tried to split along, yellow edges need a tile pushing away from the seam, purple tiles push their piece the wrong way";
+pub const TILE_NUMBER_PROPORTION: f32 = 0.4; // relative to the tile size
+pub const TILE_PRIORITY_PROPORTION: f32 = 0.25; // relative to the tile size
+pub const TILE_PRIORITY_INSET: f32 = 0.08; // relative to the tile size
+pub const SIDEBAR_TOOLTIP_SCALE: f32 = 32.0;
+pub const SIDEBAR_SHORTCUT_SCALE: f32 = 28.0;
+pub const SIDEBAR_SHORTCUT_INSET: f32 = 4.0;
 
 // colors
-pub const BOARD_BG_COLOR: Color = Color::new(106.0/255.0, 86.0/255.0, 73.0/255.0, 1.0);
-pub const SIDEBAR_BG_COLOR: Color = Color::new(0.5, 0.5, 0.5, 1.0);
+// the background colors come from the theme, see preferences.rs
 pub const BLOCK_COLOR: Color = Color::new(0.9, 0.9, 0.9, 1.0);
 // pub const BLOCK_INNER_COLOR: Color = Color::new(0.8, 0.8, 0.8, 1.0);
 pub const OUTPUT_BLOCK_COLOR: Color = Color::new(0.0, 0.0, 0.0, 1.0);
 pub const OUTPUT_OUTLINE_COLOR: Color = Color::new(0.7, 0.2, 0.2, 1.0);
 pub const SIDEBAR_COUNTER_CIRCLE_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.6);
 pub const SIDEBAR_COUNTER_TEXT_COLOR: Color = Color::new(1.0, 1.0, 1.0, 1.0);
-pub const POPUP_BG_COLOR: Color = Color::new(0.7, 0.7, 0.7, 1.0);
 pub const POPUP_TEXT_COLOR: Color = Color::new(1.0, 1.0, 1.0, 1.0);
 pub const POPUP_OVERLAY_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.5);
+pub const TILE_NUMBER_COLOR: Color = Color::new(1.0, 148.0/255.0, 0.0, 1.0);
+pub const TILE_PRIORITY_COLOR: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+pub const SEAM_COLOR: Color = Color::new(0.0, 0.9, 1.0, 0.9);
+pub const CLASHING_COLOR: Color = Color::new(1.0, 0.9, 0.0, 0.7);
+pub const WRONG_SIDE_COLOR: Color = Color::new(0.9, 0.0, 0.9, 0.5);
+pub const SIDEBAR_TOOLTIP_COLOR: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+pub const SIDEBAR_SHORTCUT_COLOR: Color = Color::new(1.0, 0.9, 0.3, 1.0);
+pub const SIDEBAR_SCROLLBAR_TRACK_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.2);
+pub const SIDEBAR_SCROLLBAR_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.6);
+pub const MINIMAP_BG_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.6);
+pub const MINIMAP_TILE_COLOR: Color = Color::new(0.6, 0.6, 0.6, 1.0);
+pub const MINIMAP_INPUT_COLOR: Color = Color::new(0.3, 0.8, 0.3, 1.0);
+pub const MINIMAP_OUTPUT_COLOR: Color = Color::new(0.7, 0.2, 0.2, 1.0);
+pub const MINIMAP_BLOCK_COLOR: Color = Color::new(0.9, 0.9, 0.9, 1.0);
+pub const MINIMAP_VIEW_COLOR: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+pub const SELECTION_COLOR: Color = Color::new(0.3, 0.6, 1.0, 0.2);
+pub const SELECTION_OUTLINE_COLOR: Color = Color::new(0.3, 0.6, 1.0, 0.9);
+
+// UX
+pub const SIDEBAR_SCROLL_SPEED: f32 = 60.0; // per notch of the mouse wheel
 
 // alpha values
 pub const BUILDING_BLOCKOBJECT_ALPHA: f32 = 0.5;
@@ -50,6 +91,22 @@ pub const HELD_OBJECT_ALPHA: f32 = 0.5;
 // in seconds unless otherwise specified
 pub const ANIMATION_DURATION: f32 = 0.4;
 
-// helpers
-// non-empty tile types
-pub const TILETYPES: [TileType; 6] = [TileType::PushTile, TileType::PrioTile, TileType::AltTile, TileType::RotTileCCW, TileType::RotTileCW, TileType::DelayTile];
+// exporting runs
+pub const EXPORT_SIZE: (u32, u32) = (640, 640); // the width has to be a multiple of 64 to read the pixels back
+pub const EXPORT_MARGIN: i32 = 1; // empty tiles around everything in an export
+pub const EXPORT_FRAMES_PER_TICK: i32 = 8;
+pub const EXPORT_END_HOLD: f32 = 1.5; // seconds to show the last frame for
+pub const EXPORT_MAX_TICKS: i32 = 500;
+pub const EXPORT_FRAMES_PER_UPDATE: i32 = 2; // more finishes sooner, but the window gets choppier
+pub const EXPORT_GIF_SPEED: i32 = 10; // 1 is the best colors, 30 is the fastest
+pub const SNAPSHOT_SIZE: (u32, u32) = (320, 320); // for thumbnails from the snapshot tool
+
+// simulation
+// sweeps sample points this far from the center of each block, and this far apart along the arc
+pub const SWEEP_SAMPLE_INSET: f32 = 0.35;
+pub const SWEEP_SAMPLE_SPACING: f32 = 0.2;
+
+// tiles
+pub const GATE_DEFAULT_PERIOD: i32 = 2;
+pub const TILE_NUMBER_MAX: i32 = 9; // so that gate periods and teleporter channels can be set with the number keys
+
@@ -0,0 +1,100 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ggez::{graphics, GameResult, GameError};
+use image::{Delay, Frame, RgbaImage};
+use image::codecs::gif::{GifEncoder, Repeat};
+
+use super::constants::*;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportFormat{
+    Gif,
+    Png // numbered frames in their own folder
+}
+
+// writes frames to disk one at a time, so a long run doesn't have to fit in memory
+pub struct Exporter{
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    frame_count: usize,
+    gif: Option<GifEncoder<File>> // None when writing pngs
+}
+
+impl Exporter{
+    pub fn new(level: &str, format: ExportFormat, width: u32, height: u32) -> GameResult<Exporter>{
+        let name = timestamped_path(EXPORT_DIR, level)?;
+
+        let (path, gif) = match format{
+            ExportFormat::Gif => {
+                let path = name.with_extension("gif");
+                let mut encoder = GifEncoder::new_with_speed(File::create(&path)?, EXPORT_GIF_SPEED);
+                encoder.set_repeat(Repeat::Infinite).map_err(image_error)?;
+                (path, Some(encoder))
+            },
+            ExportFormat::Png => {
+                fs::create_dir_all(&name)?;
+                (name, None)
+            }
+        };
+
+        Ok(Exporter{
+            path,
+            width,
+            height,
+            frame_count: 0,
+            gif
+        })
+    }
+
+    pub fn add_frame(&mut self, pixels: Vec<u8>, format: graphics::ImageFormat, seconds: f32) -> GameResult{
+        let frame = rgba_image(pixels, format, self.width, self.height)?;
+        match &mut self.gif{
+            Some(encoder) => {
+                let delay = Delay::from_numer_denom_ms((seconds * 1000.0).round() as u32, 1);
+                encoder.encode_frame(Frame::from_parts(frame, 0, 0, delay)).map_err(image_error)?;
+            },
+            None => {
+                let frame_path = self.path.join(format!("frame_{:04}", self.frame_count)).with_extension("png");
+                frame.save(frame_path).map_err(image_error)?;
+            }
+        }
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    // returns where everything went
+    pub fn finish(self) -> PathBuf{
+        // dropping the encoder writes the end of the gif
+        self.path
+    }
+}
+
+// pixels are straight out of Image::to_pixels, in the given format
+pub fn rgba_image(mut pixels: Vec<u8>, format: graphics::ImageFormat, width: u32, height: u32) -> GameResult<RgbaImage>{
+    match format{
+        graphics::ImageFormat::Rgba8Unorm | graphics::ImageFormat::Rgba8UnormSrgb => (),
+        graphics::ImageFormat::Bgra8Unorm | graphics::ImageFormat::Bgra8UnormSrgb => {
+            for pixel in pixels.chunks_exact_mut(4){
+                pixel.swap(0, 2);
+            }
+        },
+        other => return Err(GameError::RenderError(format!("Can't export images in the {:?} format", other)))
+    }
+    RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| GameError::RenderError("Image is the wrong size".to_string()))
+}
+
+// somewhere new in dir to save things from this level, without an extension
+pub fn timestamped_path(dir: &str, level: &str) -> GameResult<PathBuf>{
+    fs::create_dir_all(dir)?;
+    let time = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map_err(|e| GameError::CustomError(e.to_string()))?;
+    Ok(Path::new(dir).join(format!("{}-{}", level, time.as_millis())))
+}
+
+pub fn image_error(e: image::ImageError) -> GameError{
+    GameError::CustomError(format!("Failed to export: {}", e))
+}
@@ -1,12 +1,20 @@
-use serde::Deserialize;
+use std::fmt;
 
-#[derive(Clone, Copy, PartialEq, Eq, Deserialize, Hash, Debug)]
+use serde::{Deserialize, Serialize};
+use ggez::GameError;
+
+use super::priority::PriorityTable;
+use super::tile::TileType;
+use super::constants::SPLIT_DIAGNOSTICS_LEGEND;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash, Debug)]
 pub struct BoardPos {
     pub x: i32,
     pub y: i32
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
 pub enum Direction{
     Up,
     Down,
@@ -14,10 +22,13 @@ pub enum Direction{
     Right
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
 pub enum MovementType{
     Translation(Direction),
-    Rotation{cw: bool, around: BoardPos},
+    Rotation{quarter_turns: i32, around: BoardPos}, // positive is clockwise
+    Mirror{vertical: bool, around: BoardPos}, // vertical means across the vertical line through around
+    Teleport{dx: i32, dy: i32},
     None
 }
 
@@ -28,20 +39,183 @@ pub struct SerializedBlockObject{
     pub counter: i32
 }
 
-pub struct SimulationError{
-    pub message: String,
-    pub relevant_locations: Vec<BoardPos>
+// a player's solution to a level
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SerializedSolution{
+    pub level: String,
+    pub blockobjects: Vec<SerializedPlacement>,
+    pub tiles: Vec<SerializedTile>
+}
+
+// where a level's block object was put, by id (ids count up from 1 in the level file)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SerializedPlacement{
+    pub id: i32,
+    #[serde(flatten)]
+    pub pos: BoardPos, // the top left, after rotating
+    #[serde(default)]
+    pub quarter_turns: i32 // clockwise
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SerializedTile{
+    #[serde(rename = "type")]
+    pub tiletype: TileType,
+    #[serde(flatten)]
+    pub pos: BoardPos,
+    pub dir: Option<Direction>, // right if missing
+    pub number: Option<i32>, // the default if missing
+    pub priority_level: Option<i32> // 1 if missing
+}
+
+// older levels are just a list of block objects
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum SerializedLevel{
+    BlockObjects(Vec<SerializedBlockObject>),
+    Full{
+        blockobjects: Vec<SerializedBlockObject>,
+        #[serde(flatten)]
+        rules: LevelRules
+    }
+}
+
+// level specific changes to how the simulation works
+#[derive(Clone, Default, Deserialize)]
+pub struct LevelRules{
+    #[serde(default)]
+    pub priorities: PriorityTable,
+    #[serde(default)]
+    pub push_resolution: PushResolution
+}
+
+// what happens when a block object is pushed in several directions and can't split
+#[derive(Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PushResolution{
+    #[default]
+    Strict, // always an error
+    PushingPower // the direction with the most tiles pushing it wins, ties are still errors
+}
+
+// everything that can go wrong while running a solution
+// tick is the game tick it happened on, block object indices are into the active block objects
+pub enum SimulationError{
+    MultiPivotRotation{tick: i32, block_object: usize, pivots: Vec<BoardPos>},
+    MultiAxisMirror{tick: i32, block_object: usize, axes: Vec<BoardPos>},
+    ConflictingPush{
+        tick: i32,
+        block_object: usize,
+        tiles: Vec<BoardPos>,
+        pushing_power: Option<Vec<(Direction, i32)>>, // only when the pushing power rule ended in a tie
+        split_diagnostics: Option<Box<SplitDiagnostics>>
+    },
+    Collision{tick: i32, block_objects: Vec<usize>, locations: Vec<BoardPos>},
+    OutputOverflow{tick: i32, expected: i32, locations: Vec<BoardPos>},
+    MissingTeleporterExit{tick: i32, channel: i32, entrances: Vec<BoardPos>},
+    Stalled{tick: i32, block_objects: Vec<usize>, locations: Vec<BoardPos>}, // nothing can change anymore but the outputs aren't full
+    OutOfBounds{tick: i32, block_objects: Vec<usize>, locations: Vec<BoardPos>}, // pushed past every tile and block object the solution placed
+    Internal(String) // something that should never happen, like an empty block object
+}
+
+// why a block object pushed in several directions couldn't split, drawn over the board
+#[derive(Clone, Default)]
+pub struct SplitDiagnostics{
+    pub seams: Vec<Seam>, // every seam that was tried
+    pub clashing: Vec<(BoardPos, Direction)>, // blocks next to a seam whose pieces would run into each other, and the way that takes them apart
+    pub wrong_side: Vec<BoardPos> // push tiles pushing their piece a different way from the rest of it
+}
+
+// the line between `line` and `line + 1`, running from `start` to `end` (inclusive)
+#[derive(Clone, Copy)]
+pub struct Seam{
+    pub vertical: bool,
+    pub line: i32,
+    pub start: i32,
+    pub end: i32
 }
 
 impl SimulationError{
-    pub fn from_string(message: String) -> SimulationError{
-        SimulationError{
-            message,
-            relevant_locations: vec![]
+    // where to draw error markers
+    pub fn relevant_locations(&self) -> Vec<BoardPos>{
+        match self{
+            SimulationError::MultiPivotRotation{pivots, ..} => pivots.clone(),
+            SimulationError::MultiAxisMirror{axes, ..} => axes.clone(),
+            SimulationError::ConflictingPush{tiles, ..} => tiles.clone(),
+            SimulationError::Collision{locations, ..} => locations.clone(),
+            SimulationError::OutputOverflow{locations, ..} => locations.clone(),
+            SimulationError::MissingTeleporterExit{entrances, ..} => entrances.clone(),
+            SimulationError::Stalled{locations, ..} => locations.clone(),
+            SimulationError::OutOfBounds{locations, ..} => locations.clone(),
+            SimulationError::Internal(_) => vec![]
+        }
+    }
+
+    pub fn split_diagnostics(&self) -> Option<&SplitDiagnostics>{
+        match self{
+            SimulationError::ConflictingPush{split_diagnostics, ..} => split_diagnostics.as_deref(),
+            _ => None
+        }
+    }
+
+    // the active block objects that caused the error
+    pub fn block_objects(&self) -> Vec<usize>{
+        match self{
+            SimulationError::MultiPivotRotation{block_object, ..} => vec![*block_object],
+            SimulationError::MultiAxisMirror{block_object, ..} => vec![*block_object],
+            SimulationError::ConflictingPush{block_object, ..} => vec![*block_object],
+            SimulationError::Collision{block_objects, ..} => block_objects.clone(),
+            SimulationError::Stalled{block_objects, ..} => block_objects.clone(),
+            SimulationError::OutOfBounds{block_objects, ..} => block_objects.clone(),
+            _ => vec![]
+        }
+    }
+
+    pub fn tick(&self) -> Option<i32>{
+        match self{
+            SimulationError::MultiPivotRotation{tick, ..} => Some(*tick),
+            SimulationError::MultiAxisMirror{tick, ..} => Some(*tick),
+            SimulationError::ConflictingPush{tick, ..} => Some(*tick),
+            SimulationError::Collision{tick, ..} => Some(*tick),
+            SimulationError::OutputOverflow{tick, ..} => Some(*tick),
+            SimulationError::MissingTeleporterExit{tick, ..} => Some(*tick),
+            SimulationError::Stalled{tick, ..} => Some(*tick),
+            SimulationError::OutOfBounds{tick, ..} => Some(*tick),
+            SimulationError::Internal(_) => None
         }
     }
 }
 
+impl fmt::Display for SimulationError{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        match self{
+            SimulationError::MultiPivotRotation{..} => write!(f, "Attempted to rotate block from multiple pivots"),
+            SimulationError::MultiAxisMirror{..} => write!(f, "Attempted to mirror block across multiple axes"),
+            SimulationError::ConflictingPush{pushing_power: Some(counts), ..} => {
+                let contributions: Vec<String> = counts.iter()
+                    .map(|(dir, count)| format!("{} {}", count, dir.to_string()))
+                    .collect();
+                write!(f, "Attempted to move block in multiple directions with equal pushing power ({})", contributions.join(", "))
+            },
+            SimulationError::ConflictingPush{split_diagnostics: Some(_), ..} =>
+                write!(f, "Attempted to move block in multiple directions{}", SPLIT_DIAGNOSTICS_LEGEND),
+            SimulationError::ConflictingPush{..} => write!(f, "Attempted to move block in multiple directions"),
+            SimulationError::Collision{..} => write!(f, "Collision occured"),
+            SimulationError::OutputOverflow{expected, ..} => write!(f, "Too many objects in one output (expected {})", expected),
+            SimulationError::MissingTeleporterExit{channel, ..} => write!(f, "Teleporter channel {} has no exit", channel),
+            SimulationError::Stalled{..} => write!(f, "Everything stopped before the outputs were filled"),
+            SimulationError::OutOfBounds{..} => write!(f, "Block pushed off the edge of the board"),
+            SimulationError::Internal(message) => write!(f, "{}", message)
+        }
+    }
+}
+
+impl From<GameError> for SimulationError{
+    fn from(e: GameError) -> SimulationError{
+        SimulationError::Internal(e.to_string())
+    }
+}
+
 pub type SimulationResult<T = ()> = Result<T, SimulationError>;
 
 impl Direction {
@@ -63,6 +237,27 @@ impl Direction {
         }
     }
 
+    // flipped across a vertical line (left and right swap) or a horizontal one (up and down swap)
+    pub fn mirrored(&self, vertical: bool) -> Direction{
+        match (self, vertical){
+            (Direction::Right, true) => Direction::Left,
+            (Direction::Left, true) => Direction::Right,
+            (Direction::Down, false) => Direction::Up,
+            (Direction::Up, false) => Direction::Down,
+            (other, _) => *other
+        }
+    }
+
+    // the (dx, dy) of moving one tile in this direction
+    pub fn to_offset(self) -> (i32, i32){
+        match self{
+            Direction::Right => (1, 0),
+            Direction::Down  => (0, 1),
+            Direction::Left  => (-1, 0),
+            Direction::Up    => (0, -1)
+        }
+    }
+
     // convert to a radian counterclockwise rotation
     pub fn to_rot(&self) -> f32{
         let pi = std::f32::consts::PI;
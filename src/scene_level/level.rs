@@ -7,21 +7,24 @@ use ggez::{
     glam,
     event,
     graphics,
-    input::{mouse::MouseButton, keyboard::{KeyInput, KeyMods}, keyboard::KeyCode},
+    input::{mouse::MouseButton, keyboard::{KeyInput, KeyMods}},
     Context, GameResult, GameError
 };
 
 use super::helpers::*;
-use super::board::Board;
+use super::board::{Board, RunExport};
 use super::tile::Tile;
 use super::block::{BlockObjectMode, BlockObject, Block};
 use super::sidebar::Sidebar;
 use super::popup_box::PopupBox;
+use super::replay::{Replay, ReplayOutcome};
+use super::export::ExportFormat;
 use super::constants::*;
 
-use crate::constants::*;
 use crate::mainstate::SceneState;
 use crate::helpers::*;
+use crate::keymap::{self, Action};
+use crate::preferences;
 
 pub struct LevelState {
     board: Board,
@@ -29,6 +32,11 @@ pub struct LevelState {
     held: Holding,
     mode: LevelMode,
     popup: Option<PopupBox>,
+    rules: LevelRules,
+    levelname: String,
+    level_blockobjects: Vec<BlockObject>,
+    recording: Option<SerializedSolution>, // the solution that is running right now
+    export: Option<RunExport>, // drawn a few frames every update until it's done
     scene_channel_s: mpsc::Sender<SceneMessage>
  }
 
@@ -43,30 +51,54 @@ pub enum LevelMode {
 pub enum Holding {
     BlockObject{blockobject: BlockObject},
     Tile{tile: Tile},
+    Tiles{tiles: Vec<Tile>}, // picked up or pasted, positions are relative to the tile under the cursor
     None
 }
 
 impl LevelState {
-    pub fn new(_ctx: &mut Context, s: mpsc::Sender<SceneMessage>, levelname: &str) -> GameResult<LevelState> {
-        let blockobjects = Self::load_level(levelname)?;
+    pub fn new(ctx: &mut Context, s: mpsc::Sender<SceneMessage>, levelname: &str) -> GameResult<LevelState> {
+        let (blockobjects, rules) = Self::load_level(levelname)?;
+        let (sidebar_pos, board_pos) = Self::layout(preferences::screen_rect(ctx));
+
+        let mut board = Board::new(board_pos);
+        board.set_rules(rules.clone());
+        board.set_simulation_speed(preferences::current().simulation_speed);
 
         Ok(LevelState {
-            board: Board::new(BOARD_POS),
-            sidebar: Sidebar::new(SIDEBAR_POS, &blockobjects)?,
+            board,
+            sidebar: Sidebar::new(sidebar_pos, &blockobjects, &rules.priorities)?,
             held: Holding::None,
             mode: LevelMode::Building,
             popup: None,
+            rules,
+            levelname: levelname.to_string(),
+            level_blockobjects: blockobjects,
+            recording: None,
+            export: None,
             scene_channel_s: s
         })
     }
 
-    pub fn load_level(level_name: &str) -> GameResult<Vec<BlockObject>>{
+    // the sidebar and the board side by side, filling the screen
+    fn layout(screen: graphics::Rect) -> (graphics::Rect, graphics::Rect){
+        let sidebar_width = (screen.w * SIDEBAR_PROPORTION).round();
+        (
+            graphics::Rect::new(screen.x, screen.y, sidebar_width, screen.h),
+            graphics::Rect::new(screen.x + sidebar_width, screen.y, screen.w - sidebar_width, screen.h)
+        )
+    }
+
+    pub fn load_level(level_name: &str) -> GameResult<(Vec<BlockObject>, LevelRules)>{
         let level_path = Path::new("levels").join(level_name).with_extension("json");
         let level_string = fs::read_to_string(level_path)
             .map_err(|e: io::Error| GameError::ResourceLoadError(format!("Failed to load level data: {}", e)))?;
 
-        let level_json: Vec<SerializedBlockObject> = serde_json::from_str(&level_string[..])
+        let level_json: SerializedLevel = serde_json::from_str(&level_string[..])
             .map_err(|e: serde_json::Error| GameError::ResourceLoadError(format!("Failed to parse level data into json: {}", e)))?;
+        let (level_json, rules) = match level_json{
+            SerializedLevel::BlockObjects(blockobjects) => (blockobjects, LevelRules::default()),
+            SerializedLevel::Full{blockobjects, rules} => (blockobjects, rules)
+        };
 
         let mut id_counter = 1;
         let mut out: Vec<BlockObject> = Vec::new();
@@ -83,14 +115,15 @@ impl LevelState {
             out.push(bo);
         }
 
-        Ok(out)
+        Ok((out, rules))
     }
 }
 
 impl SceneState for LevelState {
     fn mouse_click_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> GameResult{
+        // the minimap can be clicked in every mode
+        self.board.mouse_click_event(ctx,button,x,y,&mut self.held,&self.mode)?;
         if let LevelMode::Building = self.mode{
-            self.board.mouse_click_event(ctx,button,x,y,&mut self.held)?;
             self.sidebar.mouse_click_event(ctx,button,x,y,&mut self.held)?;
         }
         Ok(())
@@ -99,16 +132,22 @@ impl SceneState for LevelState {
 
 impl event::EventHandler for LevelState {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
+        self.continue_export(ctx);
         let sim_result = self.board.update(ctx, &self.mode);
         match sim_result{
             Err(sim_err) => {
+                self.save_replay(ReplayOutcome::Error{
+                    message: sim_err.to_string(),
+                    locations: sim_err.relevant_locations()
+                });
                 self.popup = Some(PopupBox::new(
                     POPUP_WIDTH, POPUP_HEIGHT,
-                    sim_err.message
+                    sim_err.to_string()
                 ));
                 self.mode = LevelMode::Error;
             }
             Ok(true) => {
+                self.save_replay(ReplayOutcome::Victory);
                 self.popup = Some(PopupBox::new(
                     POPUP_WIDTH, POPUP_HEIGHT,
                     "You Win!!!!!!"
@@ -122,7 +161,9 @@ impl event::EventHandler for LevelState {
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let screen = preferences::screen_rect(ctx);
         let mut canvas = graphics::Canvas::from_frame(ctx, graphics::Color::new(1.0, 0.0, 1.0, 1.0));
+        canvas.set_screen_coordinates(screen);
 
         self.board.draw(ctx, &mut canvas, &self.mode)?;
         self.sidebar.draw(ctx, &mut canvas, &self.mode)?;
@@ -132,16 +173,23 @@ impl event::EventHandler for LevelState {
         let held_image = match &mut self.held{
             Holding::Tile { tile } => Some(tile.draw(ctx, HELD_TILESIZE)?),
             Holding::BlockObject { blockobject } => Some(blockobject.draw(ctx, HELD_TILESIZE)?),
+            Holding::Tiles { tiles } => {
+                self.board.draw_held_tiles(ctx, &mut canvas, tiles)?;
+                None
+            },
             Holding::None => None
         };
+        let mouse = preferences::mouse_position(ctx);
+        let held_pos = glam::vec2(mouse.x - HELD_TILESIZE/2.0, mouse.y - HELD_TILESIZE/2.0);
         if let Some(im) = held_image{
-            canvas.draw(
-                &mult_alpha(ctx, im, HELD_OBJECT_ALPHA)?,
-                glam::vec2(
-                    ctx.mouse.position().x - HELD_TILESIZE/2.0,
-                    ctx.mouse.position().y - HELD_TILESIZE/2.0
-                )
-            );
+            canvas.draw(&mult_alpha(ctx, im, HELD_OBJECT_ALPHA)?, held_pos);
+        }
+        // held tiles only look different from each other by their priority level
+        if let Holding::Tile { tile } = &self.held{
+            let priorities = &self.rules.priorities;
+            if let (true, Some(rank)) = (priorities.levelled(tile), priorities.get_rank(tile)){
+                Tile::draw_priority_label(&mut canvas, rank, held_pos, HELD_TILESIZE);
+            }
         }
 
         if self.mode == LevelMode::Error || self.mode == LevelMode::Victory{
@@ -149,7 +197,7 @@ impl event::EventHandler for LevelState {
                 &graphics::Mesh::new_rectangle(
                     ctx,
                     graphics::DrawMode::fill(),
-                    graphics::Rect::new(0.0, 0.0, SCREEN_SIZE.0, SCREEN_SIZE.1),
+                    screen,
                     POPUP_OVERLAY_COLOR
                 )?,
                 graphics::DrawParam::default()
@@ -160,8 +208,8 @@ impl event::EventHandler for LevelState {
             canvas.draw(
                 &popup.draw(ctx)?,
                 glam::vec2(
-                    (SCREEN_SIZE.0 - popup.get_width())/2.0,
-                    (SCREEN_SIZE.1 - popup.get_height())/2.0
+                    (screen.w - popup.get_width())/2.0,
+                    (screen.h - popup.get_height())/2.0
                 )
             )
         }
@@ -172,72 +220,132 @@ impl event::EventHandler for LevelState {
     }
 
     fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> GameResult{
-        self.board.mouse_button_down_event(ctx,button,x,y)?;
+        self.sidebar.mouse_button_down_event(ctx,button,x,y)?;
+        self.board.mouse_button_down_event(ctx,button,x,y,&self.mode,&self.held)?;
         Ok(())
     }
 
     fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> GameResult{
+        self.sidebar.mouse_button_up_event(ctx,button,x,y)?;
         self.board.mouse_button_up_event(ctx,button,x,y)?;
         Ok(())
     }
 
     fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, dx: f32, dy: f32) -> GameResult{
-        self.board.mouse_motion_event(ctx,x,y,dx,dy)?;
+        self.sidebar.mouse_motion_event(ctx,x,y)?;
+        self.board.mouse_motion_event(ctx,x,y,dx,dy,&self.held)?;
+        Ok(())
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) -> GameResult{
+        self.sidebar.mouse_wheel_event(ctx, x, y)?;
+        self.board.mouse_wheel_event(ctx, x, y, &self.mode)?;
+        Ok(())
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, _width: f32, _height: f32) -> GameResult{
+        let screen = preferences::screen_rect(ctx);
+        // minimised windows have no size, and there's nothing to lay out
+        if screen.w < 1.0 || screen.h < 1.0{
+            return Ok(());
+        }
+        let (sidebar_pos, board_pos) = Self::layout(screen);
+        self.sidebar.set_pos(sidebar_pos)?;
+        self.board.set_pos(board_pos);
         Ok(())
     }
 
     fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, repeated: bool) -> GameResult {
-        if input.keycode == Some(KeyCode::R) {
-            if input.mods.contains(KeyMods::SHIFT){
+        let action = keymap::action(&input);
+        // nothing else happens until the export is done, apart from cancelling it
+        if self.export.is_some(){
+            if action == Some(Action::Confirm){
+                self.export = None;
+                self.popup = None;
+            }
+            return Ok(());
+        }
+        match action{
+            Some(Action::RotateCw | Action::RotateCcw) => {
+                let clockwise = action == Some(Action::RotateCw);
                 match &mut self.held{
-                    Holding::BlockObject { blockobject } => blockobject.rotate_ccw(BoardPos{x:0,y:0}),
-                    Holding::Tile { tile } => tile.rotate_ccw(),
+                    Holding::BlockObject { blockobject } => if clockwise{
+                        blockobject.rotate_cw(BoardPos{x:0,y:0})
+                    }else{
+                        blockobject.rotate_ccw(BoardPos{x:0,y:0})
+                    },
+                    Holding::Tile { tile } => if clockwise {tile.rotate_cw()} else {tile.rotate_ccw()},
+                    Holding::Tiles { tiles } => for tile in tiles.iter_mut(){
+                        tile.apply(MovementType::Rotation{quarter_turns: if clockwise {1} else {-1}, around: BoardPos{x:0,y:0}});
+                    },
                     _other => ()
                 }
-            }else{
-                match &mut self.held{
-                    Holding::BlockObject { blockobject } => blockobject.rotate_cw(BoardPos{x:0,y:0}),
-                    Holding::Tile { tile } => tile.rotate_cw(),
-                    _other => ()
+            }
+            Some(Action::FlipHorizontal | Action::FlipVertical) => {
+                if let Holding::Tiles { tiles } = &mut self.held{
+                    // flipping left to right mirrors across a vertical line
+                    let vertical = action == Some(Action::FlipHorizontal);
+                    for tile in tiles.iter_mut(){
+                        tile.apply(MovementType::Mirror{vertical, around: BoardPos{x:0,y:0}});
+                    }
                 }
             }
-        }else if input.keycode == Some(KeyCode::Return){
-            if let Some(_) = self.popup{
-                self.popup = None
-            }else{
-                match self.mode{
-                    LevelMode::Building => {
-                        // everything has been placed
-                        if self.sidebar.num_blockobjects() == self.board.num_blockobjects(){
-                            self.process_start()?;
-                            self.mode = LevelMode::Running;
-                        }else{
-                            self.popup = Some(PopupBox::new(
-                                POPUP_WIDTH, POPUP_HEIGHT,
-                                "Must place all inputs and outputs first"
-                            ));
+            Some(Action::ExportGif | Action::ExportFrames) if self.mode == LevelMode::Building && self.popup.is_none() => {
+                let format = if action == Some(Action::ExportFrames) {ExportFormat::Png} else {ExportFormat::Gif};
+                self.export_run(format);
+            }
+            Some(Action::Snapshot) => {
+                // a picture of the board for bug reports, works in any mode
+                let message = match self.board.snapshot(ctx, &self.mode, &self.levelname){
+                    Ok(path) => format!("Saved to\n{}", path.display()),
+                    Err(e) => format!("Couldn't save the snapshot:\n{}", e)
+                };
+                self.popup = Some(PopupBox::new(POPUP_WIDTH, POPUP_HEIGHT, message));
+            }
+            Some(Action::Confirm) => {
+                if let Some(_) = self.popup{
+                    self.popup = None
+                }else{
+                    match self.mode{
+                        LevelMode::Building => {
+                            // everything has been placed
+                            if self.sidebar.num_blockobjects() == self.board.num_blockobjects(){
+                                self.process_start()?;
+                                self.mode = LevelMode::Running;
+                            }else{
+                                self.popup = Some(PopupBox::new(
+                                    POPUP_WIDTH, POPUP_HEIGHT,
+                                    "Must place all inputs and outputs first"
+                                ));
+                            }
+                        }
+                        LevelMode::Running => {
+                            self.process_end()?;
+                            self.mode = LevelMode::Building;
+                        }
+                        LevelMode::Error => {
+                            self.board.process_end()?;
+                            self.mode = LevelMode::Building;
+                        }
+                        LevelMode::Victory => {
+                            self.scene_channel_s.send(SceneMessage::EnterSceneMainMenu)
+                                .map_err(|e| GameError::CustomError(e.to_string()))?;
+                            self.mode = LevelMode::Building;
                         }
                     }
-                    LevelMode::Running => {
-                        self.process_end()?;
-                        self.mode = LevelMode::Building;
-                    }
-                    LevelMode::Error => {
-                        self.board.process_end()?;
-                        self.mode = LevelMode::Building;
-                    }
-                    LevelMode::Victory => {
-                        self.scene_channel_s.send(SceneMessage::EnterSceneMainMenu)
-                            .map_err(|e| GameError::CustomError(e.to_string()))?;
-                        self.mode = LevelMode::Building;
-                    }
                 }
             }
+            None if self.mode == LevelMode::Building && self.popup.is_none()
+                && !input.mods.contains(KeyMods::CTRL) && !self.board.numbered_tile_hovered(ctx) => {
+                // number keys over a gate or teleporter set its number instead
+                if let Some(item) = self.sidebar.shortcut(input){
+                    self.held = item;
+                }
+            }
+            _ => ()
         }
 
-        if let LevelMode::Building = self.mode{
-            self.board.key_down_event(ctx, input, repeated)?;
-        }
+        self.board.key_down_event(ctx, input, repeated, &self.mode, &mut self.held)?;
         Ok(())
     }
 }
@@ -246,12 +354,71 @@ impl LevelState{
     fn process_start(&mut self) -> GameResult{
         self.held = Holding::None;
 
+        self.recording = Some(self.board.to_solution(&self.levelname, &self.level_blockobjects)?);
         self.board.process_start()?;
         Ok(())
     }
 
     fn process_end(&mut self) -> GameResult{
+        self.save_replay(ReplayOutcome::Stopped);
         self.board.process_end()?;
         Ok(())
     }
+
+    fn export_run(&mut self, format: ExportFormat){
+        if self.sidebar.num_blockobjects() != self.board.num_blockobjects(){
+            self.popup = Some(PopupBox::new(
+                POPUP_WIDTH, POPUP_HEIGHT,
+                "Must place all inputs and outputs first"
+            ));
+            return;
+        }
+        let message = match self.board.export_run(&self.levelname, format){
+            Ok(export) => {
+                self.export = Some(export);
+                Self::export_progress(0)
+            }
+            Err(e) => format!("Couldn't export the run:\n{}", e)
+        };
+        self.popup = Some(PopupBox::new(POPUP_WIDTH, POPUP_HEIGHT, message));
+    }
+
+    // a few frames every update so the window doesn't freeze, the popup shows how far along it is
+    fn continue_export(&mut self, ctx: &mut Context){
+        let Some(mut export) = self.export.take() else{
+            return;
+        };
+        let message = match export.step(ctx, EXPORT_FRAMES_PER_UPDATE){
+            Ok(false) => {
+                let message = Self::export_progress(export.progress());
+                self.export = Some(export);
+                message
+            }
+            Ok(true) => match export.finish(ctx){
+                Ok(path) => format!("Saved to\n{}", path.display()),
+                Err(e) => format!("Couldn't export the run:\n{}", e)
+            },
+            Err(e) => format!("Couldn't export the run:\n{}", e)
+        };
+        match &mut self.popup{
+            Some(popup) => popup.set_text(graphics::Text::new(message)),
+            None => self.popup = Some(PopupBox::new(POPUP_WIDTH, POPUP_HEIGHT, message))
+        }
+    }
+
+    fn export_progress(percent: i32) -> String{
+        format!("Exporting... {}%\n(Enter to cancel)", percent)
+    }
+
+    // every run gets saved, so it can be compared against later versions of the rules
+    // losing a replay isn't worth stopping the game over, so problems only get logged
+    fn save_replay(&mut self, outcome: ReplayOutcome){
+        if let Some(solution) = self.recording.take(){
+            let saved = Replay::new(solution, self.board.get_replay_ticks().to_vec(), outcome)
+                .and_then(|replay| replay.save());
+            if let Err(e) = saved{
+                eprintln!("Couldn't save the replay: {}", e);
+            }
+        }
+    }
 }
@@ -1,6 +1,10 @@
 pub mod level;
 pub mod board;
 pub mod tile;
+pub mod tile_registry;
+pub mod priority;
+pub mod replay;
+pub mod export;
 pub mod block;
 pub mod sidebar;
 pub mod popup_box;
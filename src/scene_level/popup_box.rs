@@ -7,6 +7,7 @@ use ggez::{
 use super::constants::*;
 
 use crate::constants::*;
+use crate::preferences;
 
 pub struct PopupBox{
     width: f32,
@@ -72,7 +73,7 @@ impl PopupBox{
                 graphics::DrawMode::fill(),
                 graphics::Rect::new(0.0, 0.0, self.width, self.height),
                 POPUP_CORNER_RAD,
-                POPUP_BG_COLOR
+                preferences::theme().popup_bg
             )?,
             glam::vec2(0.0, 0.0)
         );
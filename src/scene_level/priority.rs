@@ -0,0 +1,104 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Deserialize;
+
+use super::helpers::*;
+use super::tile::{Tile, TileType};
+use super::tile_registry::TileMovement;
+
+// decides which tiles win when a block object is on top of more than one
+// levels can override the priorities from the tile registry, and can split push tiles into
+// several priority levels (1 is the top level, like the <1 <2 <3 notation in the brainstorm)
+#[derive(Clone, Deserialize)]
+#[serde(from = "SerializedPriorityTable")]
+pub struct PriorityTable{
+    overrides: HashMap<TileType, u8>,
+    push_levels: i32,
+    ranks: Vec<i32> // every priority a tile can have, highest first
+}
+
+// how a level file writes its priority table
+#[derive(Deserialize)]
+struct SerializedPriorityTable{
+    #[serde(default)]
+    overrides: HashMap<TileType, u8>,
+    #[serde(default = "default_push_levels")]
+    push_levels: i32
+}
+
+fn default_push_levels() -> i32{
+    1
+}
+
+// big enough that a higher level always beats any tile priority
+const LEVEL_STRIDE: i32 = 256;
+
+impl PriorityTable{
+    pub fn get_push_levels(&self) -> i32{
+        self.push_levels
+    }
+
+    // whether this tile can be given a priority level
+    pub fn levelled(&self, tile: &Tile) -> bool{
+        if let TileMovement::Push(_) = tile.movement(){
+            self.push_levels > 1
+        }else{
+            false
+        }
+    }
+
+    // big numbers are high priority
+    // levels come first, then the priority of the tile type
+    pub fn get_priority(&self, tile: &Tile) -> i32{
+        let tiletype = tile.get_type();
+        let base = *self.overrides.get(&tiletype).unwrap_or(&tiletype.get_priority()) as i32;
+        if base == 0{
+            // tiles that never move anything stay at the bottom
+            return 0;
+        }
+        // tiles without a level sit on the bottom level
+        let level = if self.levelled(tile) {tile.get_priority_level()} else {self.push_levels};
+        (self.push_levels - level) * LEVEL_STRIDE + base
+    }
+
+    // the number shown on tile icons, 1 is the top priority
+    // tiles that never move anything don't get one
+    pub fn get_rank(&self, tile: &Tile) -> Option<i32>{
+        let priority = self.get_priority(tile);
+        if priority == 0{
+            return None;
+        }
+        let higher = self.ranks.iter().take_while(|p| **p > priority).count() as i32;
+        Some(higher + 1)
+    }
+}
+
+// the ranks only change with the table, and get looked up for every tile every frame, so they're worked out here
+impl From<SerializedPriorityTable> for PriorityTable{
+    fn from(serialized: SerializedPriorityTable) -> PriorityTable{
+        let mut table = PriorityTable{
+            overrides: serialized.overrides,
+            push_levels: serialized.push_levels,
+            ranks: vec![]
+        };
+        let mut priorities = BTreeSet::new();
+        for tiletype in TileType::all(){
+            let mut example = Tile::new(tiletype, BoardPos{x: 0, y: 0});
+            for level in 1..=table.push_levels{
+                example.set_priority_level(level, table.push_levels);
+                priorities.insert(table.get_priority(&example));
+            }
+        }
+        table.ranks = priorities.into_iter().rev().collect();
+        table
+    }
+}
+
+impl Default for PriorityTable{
+    fn default() -> PriorityTable{
+        PriorityTable::from(SerializedPriorityTable{
+            overrides: HashMap::new(),
+            push_levels: default_push_levels()
+        })
+    }
+}
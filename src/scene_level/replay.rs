@@ -0,0 +1,353 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use ggez::{GameResult, GameError};
+
+use super::helpers::*;
+use super::block::{Block, BlockObject};
+use super::board::BoardState;
+use super::level::LevelState;
+use super::constants::{REPLAY_DIR, REPLAY_MAX_SAVED};
+
+// a recording of one run of a solution
+// the events are enough to rebuild the board at any tick without running the simulation again,
+// so an old replay can be compared against what the current rules do
+#[derive(Serialize, Deserialize)]
+pub struct Replay{
+    pub level: String,
+    pub level_hash: String, // so we can tell if the level file changed since the recording
+    pub solution: SerializedSolution,
+    pub ticks: Vec<Vec<ReplayEvent>>, // everything that happened, one list per game tick
+    pub outcome: ReplayOutcome
+}
+
+// block object indices are into the active block objects at the time of the event
+// positions are sorted, so the same run always records the same events
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayEvent{
+    Spawn{input: i32, blocks: Vec<BoardPos>}, // added to the end
+    Duplicate{from: usize, blocks: Vec<BoardPos>}, // added to the end
+    Split{from: usize, blocks: Vec<BoardPos>}, // taken out of from and added to the end
+    Merge{groups: Vec<Vec<usize>>}, // each group is added to the end, then all of them are removed
+    Move{index: usize, movement: MovementType},
+    Output{index: usize, output: i32} // removed once every output event this tick has been seen
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayOutcome{
+    Victory,
+    Error{message: String, locations: Vec<BoardPos>},
+    Stopped // the player stopped it before it finished
+}
+
+// where an old replay and a new one stop agreeing
+pub struct Divergence{
+    pub tick: usize,
+    pub old_events: Option<Vec<ReplayEvent>>, // None if that replay had already finished
+    pub new_events: Option<Vec<ReplayEvent>>,
+    pub old_outcome: ReplayOutcome,
+    pub new_outcome: ReplayOutcome,
+    pub board: Vec<Vec<BoardPos>>, // the block objects at the start of the tick, which both replays agree on
+    pub level_changed: bool
+}
+
+// rebuilds the active block objects from replay events
+#[derive(Default)]
+pub struct Playback{
+    active: Vec<Vec<BoardPos>>,
+    tick: usize
+}
+
+impl Replay{
+    pub fn new(solution: SerializedSolution, ticks: Vec<Vec<ReplayEvent>>, outcome: ReplayOutcome) -> GameResult<Replay>{
+        Ok(Replay{
+            level: solution.level.clone(),
+            level_hash: level_hash(&solution.level)?,
+            solution,
+            ticks,
+            outcome
+        })
+    }
+
+    // run a solution with the current rules and record it
+    // anything still running after max_ticks counts as stopped
+    pub fn simulate(solution: SerializedSolution, max_ticks: usize) -> GameResult<Replay>{
+        let (blockobjects, rules) = LevelState::load_level(&solution.level)?;
+        let mut state = BoardState::new();
+        state.set_rules(rules);
+        state.load_solution(&solution, &blockobjects)?;
+        state.process_start()?;
+
+        let mut outcome = ReplayOutcome::Stopped;
+        while state.get_replay_ticks().len() < max_ticks{
+            match state.process_step(){
+                Ok(true) => {
+                    outcome = ReplayOutcome::Victory;
+                    break;
+                },
+                Ok(false) => (),
+                Err(sim_err) => {
+                    outcome = ReplayOutcome::Error{message: sim_err.to_string(), locations: sim_err.relevant_locations()};
+                    break;
+                }
+            }
+        }
+        let ticks = state.get_replay_ticks().to_vec();
+        Replay::new(solution, ticks, outcome)
+    }
+
+    pub fn load(path: &Path) -> GameResult<Replay>{
+        let contents = fs::read_to_string(path)
+            .map_err(|e| GameError::ResourceLoadError(format!("Failed to load replay: {}", e)))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| GameError::ResourceLoadError(format!("Failed to parse replay: {}", e)))
+    }
+
+    // saves to the replays folder, returns where it went
+    // only the newest few are kept, every run makes one so the folder would grow forever otherwise
+    pub fn save(&self) -> GameResult<PathBuf>{
+        fs::create_dir_all(REPLAY_DIR)?;
+        let time = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_err(|e| GameError::CustomError(e.to_string()))?;
+        let path = Path::new(REPLAY_DIR).join(format!("{}-{}", self.level, time.as_millis())).with_extension("json");
+        let contents = serde_json::to_string(self)
+            .map_err(|e| GameError::CustomError(format!("Failed to write replay: {}", e)))?;
+        fs::write(&path, contents)?;
+        Self::prune(Path::new(REPLAY_DIR), REPLAY_MAX_SAVED)?;
+        Ok(path)
+    }
+
+    // deletes the oldest replays in dir until there are at most max
+    pub fn prune(dir: &Path, max: usize) -> GameResult{
+        let mut replays = vec![];
+        for entry in fs::read_dir(dir)?{
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json"){
+                replays.push((fs::metadata(&path)?.modified()?, path));
+            }
+        }
+        if replays.len() <= max{
+            return Ok(());
+        }
+        replays.sort();
+        for (_, path) in replays.iter().take(replays.len() - max){
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    // the active block objects after every tick
+    pub fn frames(&self) -> GameResult<Vec<Vec<Vec<BoardPos>>>>{
+        let mut playback = Playback::default();
+        let mut frames = vec![];
+        for events in self.ticks.iter(){
+            playback.apply_tick(events)?;
+            frames.push(playback.get_active().to_vec());
+        }
+        Ok(frames)
+    }
+
+    // compare against a newer recording of the same solution, tick by tick
+    pub fn diverge(&self, new: &Replay) -> GameResult<Option<Divergence>>{
+        let mut playback = Playback::default();
+        for tick in 0..self.ticks.len().max(new.ticks.len()){
+            let old_events = self.ticks.get(tick);
+            let new_events = new.ticks.get(tick);
+            if old_events != new_events{
+                return Ok(Some(self.divergence(new, tick, playback)));
+            }
+            if let Some(events) = old_events{
+                playback.apply_tick(events)?;
+            }
+        }
+        if self.outcome != new.outcome{
+            let tick = self.ticks.len();
+            return Ok(Some(self.divergence(new, tick, playback)));
+        }
+        Ok(None)
+    }
+
+    fn divergence(&self, new: &Replay, tick: usize, playback: Playback) -> Divergence{
+        Divergence{
+            tick,
+            old_events: self.ticks.get(tick).cloned(),
+            new_events: new.ticks.get(tick).cloned(),
+            old_outcome: self.outcome.clone(),
+            new_outcome: new.outcome.clone(),
+            board: playback.active,
+            level_changed: self.level_hash != new.level_hash
+        }
+    }
+}
+
+impl Playback{
+    pub fn get_tick(&self) -> usize{
+        self.tick
+    }
+
+    pub fn get_active(&self) -> &[Vec<BoardPos>]{
+        &self.active
+    }
+
+    // does the same things to the list of block objects that process_step did
+    pub fn apply_tick(&mut self, events: &[ReplayEvent]) -> GameResult{
+        let mut outputs = vec![];
+        for event in events.iter(){
+            match event{
+                ReplayEvent::Spawn{blocks, ..} => self.active.push(blocks.clone()),
+                ReplayEvent::Duplicate{from, blocks} => {
+                    self.get(*from)?;
+                    self.active.push(blocks.clone());
+                },
+                ReplayEvent::Split{from, blocks} => {
+                    let piece = self.get(*from)?.iter().copied().filter(|pos| blocks.contains(pos)).collect::<Vec<_>>();
+                    if piece.len() != blocks.len(){
+                        return Err(self.inconsistent(format!("block object {} doesn't have all of {:?}", from, blocks)));
+                    }
+                    self.active[*from].retain(|pos| !blocks.contains(pos));
+                    self.active.push(piece);
+                },
+                ReplayEvent::Merge{groups} => {
+                    let mut to_remove = vec![];
+                    for group in groups.iter(){
+                        let mut merged = vec![];
+                        for i in group.iter(){
+                            merged.extend(self.get(*i)?.iter().copied());
+                            to_remove.push(*i);
+                        }
+                        self.active.push(sorted(merged));
+                    }
+                    to_remove.sort_unstable_by_key(|i| std::cmp::Reverse(*i));
+                    for i in to_remove{
+                        self.active.remove(i);
+                    }
+                },
+                ReplayEvent::Move{index, movement} => {
+                    let moved = self.get(*index)?.iter().map(|pos| movement.apply(*pos)).collect();
+                    self.active[*index] = sorted(moved);
+                },
+                ReplayEvent::Output{index, ..} => {
+                    self.get(*index)?;
+                    outputs.push(*index);
+                }
+            }
+        }
+        outputs.sort_unstable_by_key(|i| std::cmp::Reverse(*i));
+        for i in outputs{
+            self.active.remove(i);
+        }
+        self.tick += 1;
+        Ok(())
+    }
+
+    fn get(&self, i: usize) -> GameResult<&Vec<BoardPos>>{
+        self.active.get(i).ok_or_else(|| self.inconsistent(format!("there is no block object {}", i)))
+    }
+
+    fn inconsistent(&self, message: String) -> GameError{
+        GameError::CustomError(format!("Replay is inconsistent at tick {}: {}", self.tick, message))
+    }
+}
+
+impl fmt::Display for Divergence{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        if self.level_changed{
+            writeln!(f, "the level file has changed since the old replay was recorded")?;
+        }
+        writeln!(f, "diverged at tick {}", self.tick)?;
+        writeln!(f, "board at the start of the tick:")?;
+        write!(f, "{}", draw_ascii(&self.board))?;
+        for (name, events, outcome) in [("old", &self.old_events, &self.old_outcome), ("new", &self.new_events, &self.new_outcome)]{
+            match events{
+                Some(events) => {
+                    writeln!(f, "{}:", name)?;
+                    for event in events.iter(){
+                        writeln!(f, "  {:?}", event)?;
+                    }
+                },
+                None => writeln!(f, "{}: finished with {:?}", name, outcome)?
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MovementType{
+    // where a position ends up after this movement
+    pub fn apply(&self, pos: BoardPos) -> BoardPos{
+        match *self{
+            MovementType::Translation(dir) => {
+                let (dx, dy) = dir.to_offset();
+                BoardPos{x: pos.x + dx, y: pos.y + dy}
+            },
+            MovementType::Rotation{quarter_turns, around} => {
+                let mut block = Block::new(pos);
+                for _ in 0..quarter_turns.rem_euclid(4){
+                    block.rotate_cw(around);
+                }
+                block.pos
+            },
+            MovementType::Mirror{vertical, around} => {
+                let mut block = Block::new(pos);
+                block.mirror(vertical, around);
+                block.pos
+            },
+            MovementType::Teleport{dx, dy} => BoardPos{x: pos.x + dx, y: pos.y + dy},
+            MovementType::None => pos
+        }
+    }
+}
+
+// the positions of a block object in a stable order
+pub fn sorted_blocks(blockobject: &BlockObject) -> Vec<BoardPos>{
+    sorted(blockobject.block_locations())
+}
+
+fn sorted(mut positions: Vec<BoardPos>) -> Vec<BoardPos>{
+    positions.sort_by_key(|pos| (pos.y, pos.x));
+    positions
+}
+
+// fnv-1a of the level file, std's hasher isn't guaranteed to stay the same between rust versions
+pub fn level_hash(level_name: &str) -> GameResult<String>{
+    let level_path = Path::new("levels").join(level_name).with_extension("json");
+    let bytes = fs::read(level_path)?;
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes{
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    Ok(format!("{:016x}", hash))
+}
+
+// one letter per block object, for printing boards in a terminal
+pub fn draw_ascii(active: &[Vec<BoardPos>]) -> String{
+    let all: Vec<&BoardPos> = active.iter().flatten().collect();
+    if all.is_empty(){
+        return "(empty)\n".to_string();
+    }
+    let xmin = all.iter().map(|pos| pos.x).min().unwrap_or(0);
+    let xmax = all.iter().map(|pos| pos.x).max().unwrap_or(0);
+    let ymin = all.iter().map(|pos| pos.y).min().unwrap_or(0);
+    let ymax = all.iter().map(|pos| pos.y).max().unwrap_or(0);
+
+    let mut out = format!("(top left is {}, {})\n", xmin, ymin);
+    for y in ymin..=ymax{
+        for x in xmin..=xmax{
+            let pos = BoardPos{x, y};
+            let owners: Vec<usize> = (0..active.len()).filter(|i| active[*i].contains(&pos)).collect();
+            out.push(match owners[..]{
+                [] => '.',
+                [i] => (b'A' + (i % 26) as u8) as char,
+                _ => '#' // overlapping, which should only happen right before a collision error
+            });
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -2,6 +2,7 @@ use ggez::{
     glam,
     graphics::{self, Image, Text, TextFragment},
     input::mouse::MouseButton,
+    input::keyboard::{KeyInput, KeyMods},
     Context, GameResult
 };
 
@@ -10,8 +11,11 @@ use super::block::BlockObject;
 use super::helpers::*;
 use super::constants::*;
 use super::level::{Holding, LevelMode};
+use super::priority::PriorityTable;
 
 use crate::constants::*;
+use crate::helpers::*;
+use crate::preferences;
 
 pub struct Sidebar{
     pos: graphics::Rect, // I'm secretly going to render everything in here
@@ -21,8 +25,10 @@ pub struct Sidebar{
     margin_x: f32,
     margin_y: f32,
     scroll_y: f32,
+    scrollbar_grab: Option<f32>, // how far down the scrollbar it's being dragged from
     tiles: Vec<Tile>,
     blockobjects: Vec<BlockObject>,
+    priorities: PriorityTable,
     rows: Vec<Box<dyn SidebarRow>>
 }
 
@@ -34,6 +40,8 @@ pub trait SidebarRow{
 
 struct SidebarRowTile{
     tiles: Vec<Tile>,
+    ranks: Vec<Option<i32>>, // the priority number drawn on each tile
+    shortcuts: Vec<Option<Shortcut>>,
     tilesize: f32,
     padding: f32 // on the side of each tile
 }
@@ -41,14 +49,34 @@ struct SidebarRowTile{
 // for now I'm going to have each BORow only contain one BlockObject
 struct SidebarRowBO{
     blockobject: BlockObject,
-    tilesize: f32
+    tilesize: f32,
+    shortcut: Option<Shortcut>
+}
+
+// a key that picks something out of the sidebar
+// tiles get the number keys and then shift+number keys, inputs and outputs get the function keys
+#[derive(Clone, Copy, PartialEq)]
+enum Shortcut{
+    Number{digit: i32, shift: bool},
+    Function(i32)
 }
 
 impl Sidebar{
-    pub fn new(pos: graphics::Rect, bos: &Vec<BlockObject>) -> GameResult<Sidebar>{
-        let tiles = TILETYPES.iter().map(|tt: &TileType| {
-            Tile::new(*tt, BoardPos{x:0, y:0})
-        }).collect();
+    pub fn new(pos: graphics::Rect, bos: &Vec<BlockObject>, priorities: &PriorityTable) -> GameResult<Sidebar>{
+        // push tiles get one copy for each priority level
+        let mut tiles = vec![];
+        for tt in TileType::all(){
+            let tile = Tile::new(tt, BoardPos{x:0, y:0});
+            if priorities.levelled(&tile){
+                for level in 1..=priorities.get_push_levels(){
+                    let mut leveltile = tile.clone();
+                    leveltile.set_priority_level(level, priorities.get_push_levels());
+                    tiles.push(leveltile);
+                }
+            }else{
+                tiles.push(tile);
+            }
+        }
 
         let mut new = Sidebar{
             pos,
@@ -58,8 +86,10 @@ impl Sidebar{
             margin_x: SIDEBAR_MARGING_X,
             margin_y: SIDEBAR_MARGIN_Y,
             scroll_y: 0.0,
+            scrollbar_grab: None,
             tiles,
             blockobjects: bos.clone(),
+            priorities: priorities.clone(),
             rows: Vec::new()
         };
 
@@ -70,48 +100,56 @@ impl Sidebar{
     pub fn set_bos(&mut self, bos: &Vec<BlockObject>) -> GameResult{
         self.blockobjects = bos.clone();
         self.init_rows()?;
-        Ok(())
+        self.scroll_to(self.scroll_y)
+    }
+
+    // when the window changes size
+    pub fn set_pos(&mut self, pos: graphics::Rect) -> GameResult{
+        self.pos = pos;
+        self.init_rows()?;
+        self.scroll_to(self.scroll_y)
     }
 
     fn init_rows(&mut self) -> GameResult{
-        let width = self.pos.w - self.margin_x*2.0;
+        self.rows.clear();
+        let width = (self.pos.w - self.margin_x*2.0).max(SIDEBAR_MIN_TILESIZE);
+        // if not even one tile fits, they get smaller
+        let tilesize = self.tilesize.min(width);
 
         // tile rows
-        let tiles_per_row = ((width+self.spacing_x)/(self.tilesize + self.spacing_x)).floor() as usize;
-        if tiles_per_row == 0{
-            // TODO: do something useful here like scaling down the tiles
-            panic!("sidebar is too small to fit tiles, fix this with an actual fix and not a panic")
-        }
+        let tiles_per_row = (((width+self.spacing_x)/(tilesize + self.spacing_x)).floor() as usize).max(1);
 
         let mut i = 0;
         while i<self.tiles.len(){
-            let mut temp_srt = SidebarRowTile::new(self.tilesize);
+            let mut temp_srt = SidebarRowTile::new(tilesize);
             for _ in 0..tiles_per_row{
                 if i<self.tiles.len(){
                     temp_srt.tiles.push(self.tiles[i].clone());
+                    temp_srt.ranks.push(self.priorities.get_rank(&self.tiles[i]));
+                    temp_srt.shortcuts.push(Shortcut::for_tile(i));
                     i += 1;
                 }
             }
             let len = temp_srt.tiles.len();
-            temp_srt.padding = (width - ((len as f32) * self.tilesize)) / ((len*2) as f32);
+            temp_srt.padding = (width - ((len as f32) * tilesize)) / ((len*2) as f32);
 
             self.rows.push(Box::new(temp_srt));
         }
 
         // blockobject rows
-        for bo in self.blockobjects.iter_mut(){
+        for (i, bo) in self.blockobjects.iter_mut().enumerate(){
             let botl = bo.get_top_left()?;
             let bobr = bo.get_bottom_right()?;
             let bowidth = 1 + bobr.x - botl.x;
             // if the block object won't fit, use a smaller tile size
-            let tilesize;
-            if self.tilesize <= width/(bowidth as f32){
-                tilesize = self.tilesize;
+            let bo_tilesize;
+            if tilesize <= width/(bowidth as f32){
+                bo_tilesize = tilesize;
             }else{
-                tilesize = width/(bowidth as f32);
+                bo_tilesize = width/(bowidth as f32);
             }
 
-            let temp_srbo = SidebarRowBO::new(tilesize, bo.clone());
+            let temp_srbo = SidebarRowBO::new(bo_tilesize, bo.clone(), Shortcut::for_blockobject(i));
             self.rows.push(Box::new(temp_srbo));
         }
         Ok(())
@@ -125,18 +163,46 @@ impl Sidebar{
             self.pos.h as u32,
             1
         );
-        let mut image_canvas = graphics::Canvas::from_image(ctx, image.clone(), SIDEBAR_BG_COLOR);
+        let mut image_canvas = graphics::Canvas::from_image(ctx, image.clone(), preferences::theme().sidebar_bg);
 
         let mut curr_y = self.margin_y;
         for row in self.rows.iter_mut(){
-            let row_image = row.draw(ctx)?;
+            let height = row.get_height()?;
+            // rows that are scrolled out of view don't need drawing
+            if curr_y + height > self.scroll_y && curr_y < self.scroll_y + self.pos.h{
+                let row_image = row.draw(ctx)?;
+
+                image_canvas.draw(
+                    &row_image,
+                    glam::vec2(self.margin_x, curr_y-self.scroll_y)
+                );
+            }
 
-            image_canvas.draw(
-                &row_image,
-                glam::vec2(self.margin_x, curr_y-self.scroll_y)
-            );
+            curr_y += height + self.spacing_y;
+        }
 
-            curr_y += row.get_height()? + self.spacing_y;
+        if let Some((track, thumb)) = self.scrollbar()?{
+            let offset = glam::vec2(-self.pos.x, -self.pos.y);
+            for (rect, color) in [(track, SIDEBAR_SCROLLBAR_TRACK_COLOR), (thumb, SIDEBAR_SCROLLBAR_COLOR)]{
+                image_canvas.draw(
+                    &graphics::Mesh::new_rounded_rectangle(
+                        ctx,
+                        graphics::DrawMode::fill(),
+                        rect,
+                        SIDEBAR_SCROLLBAR_WIDTH / 2.0,
+                        color
+                    )?,
+                    offset
+                );
+            }
+        }
+
+        // name the tile under the mouse
+        let mouse = preferences::mouse_position(ctx);
+        if let Holding::Tile{tile} = self.get_hovered(mouse.x, mouse.y)?{
+            let text = Text::new(TextFragment::new(tile.get_type().to_string())
+                .scale(SIDEBAR_TOOLTIP_SCALE).color(SIDEBAR_TOOLTIP_COLOR));
+            image_canvas.draw(&text, glam::vec2(mouse.x - self.pos.x, mouse.y - self.pos.y + SIDEBAR_TOOLTIP_SCALE));
         }
 
         image_canvas.finish(ctx)?;
@@ -153,34 +219,186 @@ impl Sidebar{
         y: f32,
         held: &mut Holding
     ) -> GameResult{
-        if self.pos.contains(glam::vec2(x, y)) && button == MouseButton::Left{
-            // find the relevant row
-            let mut curr_y = self.margin_y;
-            let mut chosen_row = None;
-            for row in self.rows.iter_mut(){
-                if y > curr_y && y < curr_y + row.get_height()?{
-                    chosen_row = Some(row);
-                    break;
-                }
-                curr_y += row.get_height()? + self.spacing_y;
-            }
-            *held = match chosen_row{
-                Some(row) => row.get_held(x-self.margin_x, y-curr_y)?,
-                None => Holding::None
-            }
+        // clicking the scrollbar shouldn't drop whatever is held
+        let on_scrollbar = self.scrollbar()?.is_some_and(|(track, _)| track.contains(glam::vec2(x, y)));
+        if self.pos.contains(glam::vec2(x, y)) && button == MouseButton::Left && !on_scrollbar{
+            *held = self.get_hovered(x, y)?;
+        }
+        Ok(())
+    }
+
+    pub fn mouse_button_down_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> GameResult{
+        let Some((track, thumb)) = self.scrollbar()? else{
+            return Ok(());
+        };
+        if button != MouseButton::Left{
+            return Ok(());
+        }
+        if thumb.contains(glam::vec2(x, y)){
+            self.scrollbar_grab = Some(y - thumb.y);
+        }else if track.contains(glam::vec2(x, y)){
+            // clicking the track jumps there, and it can be dragged from there too
+            self.scrollbar_grab = Some(thumb.h / 2.0);
+            self.drag_scrollbar(y)?;
+        }
+        Ok(())
+    }
+
+    pub fn mouse_button_up_event(&mut self, _ctx: &mut Context, _button: MouseButton, _x: f32, _y: f32) -> GameResult{
+        self.scrollbar_grab = None;
+        Ok(())
+    }
+
+    pub fn mouse_motion_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) -> GameResult{
+        if self.scrollbar_grab.is_some(){
+            self.drag_scrollbar(y)?;
         }
         Ok(())
     }
 
+    pub fn mouse_wheel_event(&mut self, ctx: &mut Context, _x: f32, y: f32) -> GameResult{
+        if self.pos.contains(preferences::mouse_position(ctx)){
+            self.scroll_to(self.scroll_y - y * SIDEBAR_SCROLL_SPEED)?;
+        }
+        Ok(())
+    }
+
+    // moves the scrollbar so that the point it was grabbed at is at y
+    fn drag_scrollbar(&mut self, y: f32) -> GameResult{
+        let (Some((track, thumb)), Some(grab)) = (self.scrollbar()?, self.scrollbar_grab) else{
+            return Ok(());
+        };
+        let proportion = (y - grab - track.y) / (track.h - thumb.h);
+        let max_scroll = self.max_scroll()?;
+        self.scroll_to(proportion * max_scroll)
+    }
+
+    fn scroll_to(&mut self, scroll_y: f32) -> GameResult{
+        self.scroll_y = scroll_y.clamp(0.0, self.max_scroll()?);
+        Ok(())
+    }
+
+    // how tall all the rows are, with the margins
+    fn content_height(&mut self) -> GameResult<f32>{
+        let mut height = self.margin_y * 2.0;
+        for row in self.rows.iter_mut(){
+            height += row.get_height()? + self.spacing_y;
+        }
+        if !self.rows.is_empty(){
+            height -= self.spacing_y;
+        }
+        Ok(height)
+    }
+
+    fn max_scroll(&mut self) -> GameResult<f32>{
+        Ok((self.content_height()? - self.pos.h).max(0.0))
+    }
+
+    // the track and the part that gets dragged in screen coordinates, None if everything fits without scrolling
+    fn scrollbar(&mut self) -> GameResult<Option<(graphics::Rect, graphics::Rect)>>{
+        let content_height = self.content_height()?;
+        if content_height <= self.pos.h{
+            return Ok(None);
+        }
+        let track = graphics::Rect::new(
+            self.pos.right() - SIDEBAR_SCROLLBAR_WIDTH - SIDEBAR_SCROLLBAR_INSET,
+            self.pos.y + SIDEBAR_SCROLLBAR_INSET,
+            SIDEBAR_SCROLLBAR_WIDTH,
+            self.pos.h - SIDEBAR_SCROLLBAR_INSET * 2.0
+        );
+        // as long as the visible part is compared to everything
+        let length = (track.h * self.pos.h / content_height).max(SIDEBAR_SCROLLBAR_MIN_LENGTH).min(track.h);
+        let top = track.y + (track.h - length) * self.scroll_y / (content_height - self.pos.h);
+        Ok(Some((track, graphics::Rect::new(track.x, top, track.w, length))))
+    }
+
+    // whatever would get picked up by clicking at (x, y)
+    fn get_hovered(&mut self, x: f32, y: f32) -> GameResult<Holding>{
+        if !self.pos.contains(glam::vec2(x, y)){
+            return Ok(Holding::None);
+        }
+        // where it is on the scrolled rows, rather than on the screen
+        let (x, y) = (x - self.pos.x, y - self.pos.y + self.scroll_y);
+        // find the relevant row
+        let mut curr_y = self.margin_y;
+        let mut chosen_row = None;
+        for row in self.rows.iter_mut(){
+            if y > curr_y && y < curr_y + row.get_height()?{
+                chosen_row = Some(row);
+                break;
+            }
+            curr_y += row.get_height()? + self.spacing_y;
+        }
+        match chosen_row{
+            Some(row) => row.get_held(x-self.margin_x, y-curr_y),
+            None => Ok(Holding::None)
+        }
+    }
+
     pub fn num_blockobjects(&self) -> usize{
         self.blockobjects.len()
     }
+
+    // whatever a shortcut key picks up, None if the key isn't a shortcut
+    pub fn shortcut(&self, input: KeyInput) -> Option<Holding>{
+        let shortcut = Shortcut::from_key(input)?;
+        if let Some(i) = (0..self.tiles.len()).find(|i| Shortcut::for_tile(*i) == Some(shortcut)){
+            return Some(Holding::Tile{tile: self.tiles[i].clone()});
+        }
+        (0..self.blockobjects.len()).find(|i| Shortcut::for_blockobject(*i) == Some(shortcut))
+            .map(|i| Holding::BlockObject{blockobject: self.blockobjects[i].clone()})
+    }
+}
+
+impl Shortcut{
+    // 1 to 9 then 0, then the same again with shift
+    fn for_tile(i: usize) -> Option<Shortcut>{
+        let digit = (i as i32 + 1) % 10;
+        match i{
+            0..=9 => Some(Shortcut::Number{digit, shift: false}),
+            10..=19 => Some(Shortcut::Number{digit, shift: true}),
+            _ => None
+        }
+    }
+
+    fn for_blockobject(i: usize) -> Option<Shortcut>{
+        // F12 takes snapshots
+        if i < 11 {Some(Shortcut::Function(i as i32 + 1))} else {None}
+    }
+
+    fn from_key(input: KeyInput) -> Option<Shortcut>{
+        let keycode = input.keycode?;
+        if let Some(digit) = number_key_value(keycode){
+            Some(Shortcut::Number{digit, shift: input.mods.contains(KeyMods::SHIFT)})
+        }else{
+            function_key_value(keycode).map(Shortcut::Function)
+        }
+    }
+
+    fn label(&self) -> String{
+        match self{
+            Shortcut::Number{digit, shift: false} => digit.to_string(),
+            // the font doesn't have a proper shift symbol
+            Shortcut::Number{digit, shift: true} => format!("\u{2191}{}", digit),
+            Shortcut::Function(n) => format!("F{}", n)
+        }
+    }
+
+    // pos is where the corner of the text goes, and align says which corner it is
+    fn draw(&self, canvas: &mut graphics::Canvas, pos: glam::Vec2, align: graphics::TextAlign){
+        let mut text = Text::new(TextFragment::new(self.label())
+            .scale(SIDEBAR_SHORTCUT_SCALE).color(SIDEBAR_SHORTCUT_COLOR));
+        text.set_layout(graphics::TextLayout{h_align: align, v_align: align});
+        canvas.draw(&text, pos);
+    }
 }
 
 impl SidebarRowTile{
     fn new(tilesize: f32) -> SidebarRowTile{
         SidebarRowTile{
             tiles: Vec::new(),
+            ranks: Vec::new(),
+            shortcuts: Vec::new(),
             tilesize,
             padding: 0.0
         }
@@ -207,6 +425,14 @@ impl SidebarRow for SidebarRowTile{
                 &tile.draw(ctx, self.tilesize)?,
                 glam::vec2(xpos, 0.0)
             );
+            if let Some(rank) = self.ranks[i]{
+                Tile::draw_priority_label(&mut image_canvas, rank, glam::vec2(xpos, 0.0), self.tilesize);
+            }
+            // bottom right, out of the way of the priority label and the number
+            if let Some(shortcut) = self.shortcuts[i]{
+                let corner = glam::vec2(xpos + self.tilesize, self.tilesize) - glam::vec2(SIDEBAR_SHORTCUT_INSET, SIDEBAR_SHORTCUT_INSET);
+                shortcut.draw(&mut image_canvas, corner, graphics::TextAlign::End);
+            }
         }
 
         image_canvas.finish(ctx)?;
@@ -229,10 +455,11 @@ impl SidebarRow for SidebarRowTile{
 }
 
 impl SidebarRowBO{
-    fn new(tilesize:f32, blockobject: BlockObject) -> SidebarRowBO{
+    fn new(tilesize:f32, blockobject: BlockObject, shortcut: Option<Shortcut>) -> SidebarRowBO{
         SidebarRowBO{
             blockobject,
             tilesize,
+            shortcut
         }
     }
 }
@@ -260,6 +487,10 @@ impl SidebarRow for SidebarRowBO{
             &self.blockobject.draw(ctx, self.tilesize)?,
             glam::vec2(0.0, 0.0)
         );
+        // top left, since the counter goes in the bottom right
+        if let Some(shortcut) = self.shortcut{
+            shortcut.draw(&mut image_canvas, glam::vec2(SIDEBAR_SHORTCUT_INSET, SIDEBAR_SHORTCUT_INSET), graphics::TextAlign::Begin);
+        }
 
         // draw the number if we need to
         if self.blockobject.start_counter != 1{
@@ -1,30 +1,36 @@
 use super::helpers::*;
 
 use ggez::{
-    graphics::Image,
+    glam,
+    graphics::{self, Image, Text, TextFragment},
     Context, GameResult
 };
 
+use super::constants::*;
+use super::block::BlockObject;
+use super::tile_registry::*;
+
+use std::fmt;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::asset_cache;
+use crate::constants::*;
 
 #[derive(Clone)]
 pub struct Tile {
     tiletype: TileType,
     dir: Direction,
     pos: BoardPos,
-    orinal_dir: Direction // only used by alternating tiles
+    orinal_dir: Direction, // only used by alternating tiles
+    number: i32, // the period of gate tiles and the channel of teleporters
+    counter: i32, // only used by gate tiles
+    original_counter: i32,
+    priority_level: i32 // only used by push tiles in levels with several priority levels
 }
 
+// an index into the tile registry
 #[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
-pub enum TileType{
-    Empty,
-    PushTile,
-    PrioTile,
-    AltTile,
-    RotTileCW,
-    RotTileCCW,
-    DelayTile
-}
+pub struct TileType(usize);
 
 impl Tile{
     pub fn new(tiletype: TileType, pos: BoardPos) -> Tile{
@@ -33,6 +39,10 @@ impl Tile{
             dir: Direction::Right,
             pos,
             orinal_dir: Direction::Right,
+            number: tiletype.default_number(),
+            counter: 0,
+            original_counter: 0,
+            priority_level: 1
         }
     }
 
@@ -42,6 +52,10 @@ impl Tile{
             dir,
             pos,
             orinal_dir: dir,
+            number: tiletype.default_number(),
+            counter: 0,
+            original_counter: 0,
+            priority_level: 1
         }
     }
 
@@ -81,22 +95,86 @@ impl Tile{
         self.dir = dir;
     }
 
+    // move this tile as one of a group of tiles being moved together, turning it to match
+    pub fn apply(&mut self, movement: MovementType){
+        self.pos = movement.apply(self.pos);
+        match movement{
+            MovementType::Rotation{quarter_turns, ..} => {
+                for _ in 0..quarter_turns.rem_euclid(4){
+                    self.dir = self.dir.clockwise();
+                    self.orinal_dir = self.orinal_dir.clockwise();
+                    self.tiletype = self.tiletype.turned();
+                }
+            },
+            MovementType::Mirror{vertical, ..} => {
+                self.dir = self.dir.mirrored(vertical);
+                self.orinal_dir = self.orinal_dir.mirrored(vertical);
+                self.tiletype = self.tiletype.mirrored();
+            },
+            MovementType::Translation(_) | MovementType::Teleport{..} | MovementType::None => ()
+        }
+    }
+
+    pub fn get_number(&self) -> i32{
+        self.number
+    }
+
+    pub fn set_number(&mut self, number: i32){
+        self.number = number.clamp(1, TILE_NUMBER_MAX);
+    }
+
+    pub fn get_priority_level(&self) -> i32{
+        self.priority_level
+    }
+
+    // levels outside the ones the level has would sort above the top level or below the bottom one
+    pub fn set_priority_level(&mut self, priority_level: i32, push_levels: i32){
+        self.priority_level = priority_level.clamp(1, push_levels.max(1));
+    }
+
     pub fn draw(&self, ctx: &mut Context, tilesize: f32) -> GameResult<Image>{
-        let mut image_name = match self.tiletype{
-            TileType::Empty => "empty_tile",
-            TileType::PushTile => "push_tile",
-            TileType::PrioTile => "prio_tile",
-            TileType::AltTile => "alt_tile",
-            TileType::RotTileCW => "rot_tile_cw",
-            TileType::RotTileCCW => "rot_tile_ccw",
-            TileType::DelayTile => "delay_tile"
-        }.to_string();
+        let mut image_name = self.tiletype.kind().sprite.to_string();
 
         if self.tiletype.rotatable(){
             image_name = image_name + "_" + self.dir.to_string();
         }
 
-        asset_cache::get_scaled_image(ctx, image_name, tilesize)
+        let image = asset_cache::get_scaled_image(ctx, image_name, tilesize)?;
+
+        if self.tiletype.numbered(){
+            self.draw_number(ctx, image, tilesize)
+        }else{
+            Ok(image)
+        }
+    }
+
+    // write the number of a gate or teleporter on top of its image
+    fn draw_number(&self, ctx: &mut Context, base_image: Image, tilesize: f32) -> GameResult<Image>{
+        let color_format = ctx.gfx.surface_format();
+        let image = Image::new_canvas_image(
+            ctx, color_format,
+            tilesize.ceil() as u32,
+            tilesize.ceil() as u32,
+            1
+        );
+        let mut image_canvas = graphics::Canvas::from_image(ctx, image.clone(), TRANSPARENT_COLOR);
+
+        image_canvas.draw(&base_image, graphics::DrawParam::default());
+
+        let mut text = Text::new(TextFragment::new(self.number.to_string())
+            .scale(tilesize * TILE_NUMBER_PROPORTION).color(TILE_NUMBER_COLOR));
+        text.set_layout(graphics::TextLayout::center());
+        image_canvas.draw(&text, glam::vec2(tilesize/2.0, tilesize/2.0));
+
+        image_canvas.finish(ctx)?;
+        Ok(image)
+    }
+
+    // write a priority number in the top left corner of a tile drawn at pos
+    pub fn draw_priority_label(canvas: &mut graphics::Canvas, rank: i32, pos: glam::Vec2, tilesize: f32){
+        let text = Text::new(TextFragment::new(rank.to_string())
+            .scale(tilesize * TILE_PRIORITY_PROPORTION).color(TILE_PRIORITY_COLOR));
+        canvas.draw(&text, pos + glam::vec2(tilesize, tilesize) * TILE_PRIORITY_INSET);
     }
 
     pub fn save_dir(&mut self){
@@ -110,55 +188,111 @@ impl Tile{
     pub fn flip_dir(&mut self){
         self.dir = self.dir.clockwise().clockwise();
     }
+
+    pub fn save_counter(&mut self){
+        self.original_counter = self.counter;
+    }
+
+    pub fn load_counter(&mut self){
+        self.counter = self.original_counter;
+    }
+
+    // how many block objects a gate tile has stopped since it last let one through
+    pub fn get_counter(&self) -> i32{
+        self.counter
+    }
+
+    pub fn set_counter(&mut self, counter: i32){
+        self.counter = counter;
+    }
+
+    // the hooks below just hand the tile to its behaviour in the registry
+
+    pub fn movement(&self) -> TileMovement{
+        self.tiletype.kind().behaviour.movement(self)
+    }
+
+    pub fn ignores_just_moved(&self) -> bool{
+        self.tiletype.kind().behaviour.ignores_just_moved()
+    }
+
+    pub fn lets_through(&self, arrival: i32) -> bool{
+        self.tiletype.kind().behaviour.lets_through(self, arrival)
+    }
+
+    pub fn on_arrive(&self, blockobject: &mut BlockObject) -> SimulationResult<Option<BlockObject>>{
+        self.tiletype.kind().behaviour.on_arrive(self, blockobject)
+    }
+
+    pub fn after_use(&mut self){
+        self.tiletype.kind().behaviour.after_use(self)
+    }
+
+    pub fn exit_channel(&self) -> Option<i32>{
+        self.tiletype.kind().behaviour.exit_channel(self)
+    }
 }
 
 impl TileType {
+    // every registered tile type, in sidebar order
+    pub fn all() -> impl Iterator<Item = TileType>{
+        (0..TILE_REGISTRY.len()).map(TileType)
+    }
+
+    pub fn from_id(id: &str) -> Option<TileType>{
+        TileType::all().find(|tiletype| tiletype.kind().id == id)
+    }
+
+    pub fn kind(&self) -> &'static TileKind{
+        &TILE_REGISTRY[self.0]
+    }
+
     // big numbers are high priority
+    // only the default, the simulator asks the level's PriorityTable which can override it
     pub fn get_priority(&self) -> u8{
-        match self{
-            TileType::Empty => 0,
-            TileType::PushTile => 3,
-            TileType::PrioTile => 4,
-            TileType::AltTile => 3,
-            TileType::RotTileCW => 1,
-            TileType::RotTileCCW => 1,
-            TileType::DelayTile => 2 // this should never get into prio fights
-        }
+        self.kind().priority
     }
 
     pub fn rotatable(&self) -> bool{
-        match self{
-            TileType::Empty => false,
-            TileType::PushTile => true,
-            TileType::PrioTile => true,
-            TileType::AltTile => true,
-            TileType::RotTileCW => false,
-            TileType::RotTileCCW => false,
-            TileType::DelayTile => true
-        }
+        self.kind().rotatable
     }
 
-    pub fn is_push_tile(&self) -> bool{
-        match self{
-            TileType::Empty => false,
-            TileType::PushTile => true,
-            TileType::PrioTile => true,
-            TileType::AltTile => true,
-            TileType::RotTileCW => false,
-            TileType::RotTileCCW => false,
-            TileType::DelayTile => true
-        }
+    // numbered tiles have a number that can be changed while building
+    pub fn numbered(&self) -> bool{
+        self.kind().default_number.is_some()
     }
 
-    pub fn is_rot_tile(&self) -> bool{
-        match self{
-            TileType::Empty => false,
-            TileType::PushTile => false,
-            TileType::PrioTile => false,
-            TileType::AltTile => false,
-            TileType::RotTileCW => true,
-            TileType::RotTileCCW => true,
-            TileType::DelayTile => false
-        }
+    pub fn default_number(&self) -> i32{
+        self.kind().default_number.unwrap_or(0)
+    }
+
+    // what this tile is after a group of tiles is turned a quarter
+    pub fn turned(&self) -> TileType{
+        self.kind().turned.and_then(TileType::from_id).unwrap_or(*self)
+    }
+
+    // what this tile is after a group of tiles is mirrored
+    pub fn mirrored(&self) -> TileType{
+        self.kind().mirrored.and_then(TileType::from_id).unwrap_or(*self)
+    }
+}
+
+impl fmt::Display for TileType{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        write!(f, "{}", self.kind().name)
+    }
+}
+
+// tile types are saved as their registry id
+impl Serialize for TileType{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>{
+        serializer.serialize_str(self.kind().id)
+    }
+}
+
+impl<'de> Deserialize<'de> for TileType{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TileType, D::Error>{
+        let id = String::deserialize(deserializer)?;
+        TileType::from_id(&id).ok_or_else(|| de::Error::custom(format!("Unknown tile type {}", id)))
     }
 }
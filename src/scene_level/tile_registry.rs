@@ -0,0 +1,308 @@
+use super::helpers::*;
+use super::tile::Tile;
+use super::block::BlockObject;
+use super::constants::*;
+
+// everything about a kind of tile lives in its registry entry
+// a new tile that moves things the way an existing one does (push, rotate, mirror, teleport) only needs an entry here
+// and its sprites in assets/images, a new kind of movement also needs a TileMovement variant and handling in Board::process_step
+pub struct TileKind{
+    pub id: &'static str, // used when saving and loading
+    pub name: &'static str, // shown to the player
+    pub sprite: &'static str, // the direction gets appended for rotatable tiles
+    pub priority: u8, // big numbers are high priority, this is only the default since levels can override it in their PriorityTable
+    pub rotatable: bool,
+    pub default_number: Option<i32>, // only numbered tiles (gates, teleporters) have one
+    // for tiles that are one of a pair, the id of the other one
+    // when a group of tiles gets turned a quarter, or mirrored, these swap so the group still does the same thing
+    pub turned: Option<&'static str>,
+    pub mirrored: Option<&'static str>,
+    pub behaviour: &'static dyn TileBehaviour
+}
+
+// the order here is the order in the sidebar
+pub static TILE_REGISTRY: [TileKind; 13] = [
+    TileKind{
+        id: "push_tile",
+        name: "Push",
+        sprite: "push_tile",
+        priority: 3,
+        rotatable: true,
+        default_number: None,
+        turned: None,
+        mirrored: None,
+        behaviour: &PushBehaviour
+    },
+    TileKind{
+        id: "prio_tile",
+        name: "Priority Push",
+        sprite: "prio_tile",
+        priority: 4,
+        rotatable: true,
+        default_number: None,
+        turned: None,
+        mirrored: None,
+        behaviour: &PushBehaviour
+    },
+    TileKind{
+        id: "alt_tile",
+        name: "Alternating Push",
+        sprite: "alt_tile",
+        priority: 3,
+        rotatable: true,
+        default_number: None,
+        turned: None,
+        mirrored: None,
+        behaviour: &AltBehaviour
+    },
+    TileKind{
+        id: "rot_tile_ccw",
+        name: "Rotate Counterclockwise",
+        sprite: "rot_tile_ccw",
+        priority: 1,
+        rotatable: false,
+        default_number: None,
+        turned: None,
+        mirrored: Some("rot_tile_cw"),
+        behaviour: &RotateBehaviour{quarter_turns: -1}
+    },
+    TileKind{
+        id: "rot_tile_cw",
+        name: "Rotate Clockwise",
+        sprite: "rot_tile_cw",
+        priority: 1,
+        rotatable: false,
+        default_number: None,
+        turned: None,
+        mirrored: Some("rot_tile_ccw"),
+        behaviour: &RotateBehaviour{quarter_turns: 1}
+    },
+    TileKind{
+        id: "rot_tile_180",
+        name: "Half Turn",
+        sprite: "rot_tile_180",
+        priority: 1,
+        rotatable: false,
+        default_number: None,
+        turned: None,
+        mirrored: None,
+        behaviour: &RotateBehaviour{quarter_turns: 2}
+    },
+    TileKind{
+        id: "mirror_tile_hori",
+        name: "Mirror Horizontally",
+        sprite: "mirror_tile_hori",
+        priority: 1,
+        rotatable: false,
+        default_number: None,
+        turned: Some("mirror_tile_vert"),
+        mirrored: None,
+        behaviour: &MirrorBehaviour{vertical: false}
+    },
+    TileKind{
+        id: "mirror_tile_vert",
+        name: "Mirror Vertically",
+        sprite: "mirror_tile_vert",
+        priority: 1,
+        rotatable: false,
+        default_number: None,
+        turned: Some("mirror_tile_hori"),
+        mirrored: None,
+        behaviour: &MirrorBehaviour{vertical: true}
+    },
+    TileKind{
+        id: "delay_tile",
+        name: "Delay",
+        sprite: "delay_tile",
+        priority: 2, // this should never get into prio fights
+        rotatable: true,
+        default_number: None,
+        turned: None,
+        mirrored: None,
+        behaviour: &DelayBehaviour
+    },
+    TileKind{
+        id: "gate_tile",
+        name: "Gate",
+        sprite: "gate_tile",
+        priority: 0, // gates never move anything themselves
+        rotatable: false,
+        default_number: Some(GATE_DEFAULT_PERIOD),
+        turned: None,
+        mirrored: None,
+        behaviour: &GateBehaviour
+    },
+    TileKind{
+        id: "dup_tile",
+        name: "Duplicator",
+        sprite: "dup_tile",
+        priority: 0, // duplicators never move anything themselves
+        rotatable: true,
+        default_number: None,
+        turned: None,
+        mirrored: None,
+        behaviour: &DupBehaviour
+    },
+    TileKind{
+        id: "tele_in_tile",
+        name: "Teleporter Entrance",
+        sprite: "tele_in_tile",
+        priority: 0, // only does anything if it's the only kind of tile under a block object
+        rotatable: false,
+        default_number: Some(1),
+        turned: None,
+        mirrored: None,
+        behaviour: &TeleEntranceBehaviour
+    },
+    TileKind{
+        id: "tele_out_tile",
+        name: "Teleporter Exit",
+        sprite: "tele_out_tile",
+        priority: 3,
+        rotatable: true,
+        default_number: Some(1),
+        turned: None,
+        mirrored: None,
+        behaviour: &TeleExitBehaviour
+    },
+];
+
+// how a tile wants to move the block object on top of it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TileMovement{
+    None,
+    Push(Direction),
+    Rotate{quarter_turns: i32}, // around the tile, positive is clockwise
+    Mirror{vertical: bool}, // across a line through the tile
+    Teleport{channel: i32} // only happens if the whole block object is on entrances
+}
+
+// hooks that the simulator calls every tick, the defaults do nothing
+pub trait TileBehaviour: Sync{
+    fn movement(&self, _tile: &Tile) -> TileMovement{
+        TileMovement::None
+    }
+
+    // if true, block objects that moved last tick are not affected by this tile
+    fn ignores_just_moved(&self) -> bool{
+        false
+    }
+
+    // a block object is trying to move onto this tile, and it's the arrival'th one to try since the last one got on
+    // returning false stops it like a wall, the board does the counting once it knows what moved
+    // rotations and mirrors that would pass over the tile ask too, but never count
+    fn lets_through(&self, _tile: &Tile, _arrival: i32) -> bool{
+        true
+    }
+
+    // a block object was just pushed so that it covers this tile
+    // returns a new block object to spawn, if any
+    fn on_arrive(&self, _tile: &Tile, _blockobject: &mut BlockObject) -> SimulationResult<Option<BlockObject>>{
+        Ok(None)
+    }
+
+    // the tile was one of the tiles deciding how something moved this tick
+    fn after_use(&self, _tile: &mut Tile){}
+
+    // teleporter exits return the channel they are the exit for
+    fn exit_channel(&self, _tile: &Tile) -> Option<i32>{
+        None
+    }
+}
+
+struct PushBehaviour;
+
+impl TileBehaviour for PushBehaviour{
+    fn movement(&self, tile: &Tile) -> TileMovement{
+        TileMovement::Push(tile.get_dir())
+    }
+}
+
+struct AltBehaviour;
+
+impl TileBehaviour for AltBehaviour{
+    fn movement(&self, tile: &Tile) -> TileMovement{
+        TileMovement::Push(tile.get_dir())
+    }
+
+    fn after_use(&self, tile: &mut Tile){
+        tile.flip_dir();
+    }
+}
+
+struct DelayBehaviour;
+
+impl TileBehaviour for DelayBehaviour{
+    fn movement(&self, tile: &Tile) -> TileMovement{
+        TileMovement::Push(tile.get_dir())
+    }
+
+    fn ignores_just_moved(&self) -> bool{
+        true
+    }
+}
+
+struct RotateBehaviour{
+    quarter_turns: i32
+}
+
+impl TileBehaviour for RotateBehaviour{
+    fn movement(&self, _tile: &Tile) -> TileMovement{
+        TileMovement::Rotate{quarter_turns: self.quarter_turns}
+    }
+}
+
+struct MirrorBehaviour{
+    vertical: bool
+}
+
+impl TileBehaviour for MirrorBehaviour{
+    fn movement(&self, _tile: &Tile) -> TileMovement{
+        TileMovement::Mirror{vertical: self.vertical}
+    }
+}
+
+struct GateBehaviour;
+
+impl TileBehaviour for GateBehaviour{
+    // every nth block object gets through, the ones stopped before it wait there for good
+    fn lets_through(&self, tile: &Tile, arrival: i32) -> bool{
+        arrival >= tile.get_number()
+    }
+}
+
+struct DupBehaviour;
+
+impl TileBehaviour for DupBehaviour{
+    // the copy goes right next to the original, on the side the tile is pointing
+    fn on_arrive(&self, tile: &Tile, blockobject: &mut BlockObject) -> SimulationResult<Option<BlockObject>>{
+        let tl = blockobject.get_top_left()?;
+        let br = blockobject.get_bottom_right()?;
+        let (dx, dy) = tile.get_dir().to_offset();
+        let mut bocopy = blockobject.clone();
+        bocopy.translate(dx * (1 + br.x - tl.x), dy * (1 + br.y - tl.y));
+        bocopy.just_moved = true;
+        bocopy.gate_arrivals.clear(); // the copy hasn't been anywhere yet
+        Ok(Some(bocopy))
+    }
+}
+
+struct TeleEntranceBehaviour;
+
+impl TileBehaviour for TeleEntranceBehaviour{
+    fn movement(&self, tile: &Tile) -> TileMovement{
+        TileMovement::Teleport{channel: tile.get_number()}
+    }
+}
+
+struct TeleExitBehaviour;
+
+impl TileBehaviour for TeleExitBehaviour{
+    fn movement(&self, tile: &Tile) -> TileMovement{
+        TileMovement::Push(tile.get_dir())
+    }
+
+    fn exit_channel(&self, tile: &Tile) -> Option<i32>{
+        Some(tile.get_number())
+    }
+}
@@ -3,13 +3,15 @@ use std::sync::mpsc;
 use ggez::{
     event,
     graphics,
-    input::{mouse::MouseButton, keyboard::{KeyInput, KeyMods}, keyboard::KeyCode},
+    input::{mouse::MouseButton, keyboard::{KeyInput, KeyMods}},
     Context, GameResult, GameError
 };
 
 use crate::constants::*;
 use crate::mainstate::SceneState;
 use crate::helpers::*;
+use crate::keymap::{self, Action};
+use crate::preferences;
 
 
 pub struct MainMenuState {
@@ -36,7 +38,8 @@ impl event::EventHandler for MainMenuState {
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        let canvas = graphics::Canvas::from_frame(ctx, graphics::Color::new(1.0, 0.0, 1.0, 1.0));
+        let mut canvas = graphics::Canvas::from_frame(ctx, graphics::Color::new(1.0, 0.0, 1.0, 1.0));
+        canvas.set_screen_coordinates(preferences::screen_rect(ctx));
         canvas.finish(ctx)?;
         Ok(())
     }
@@ -46,9 +49,16 @@ impl event::EventHandler for MainMenuState {
     }
 
     fn key_down_event(&mut self, _ctx: &mut Context, input: KeyInput, _repeated: bool) -> GameResult {
-        if input.keycode == Some(KeyCode::Return){
-            self.scene_channel_s.send(SceneMessage::EnterSceneLevel { levelname: "Testlevel2".to_string() })
-                .map_err(|e| GameError::CustomError(e.to_string()))?;
+        match keymap::action(&input){
+            Some(Action::Confirm) => {
+                self.scene_channel_s.send(SceneMessage::EnterSceneLevel { levelname: "Testlevel2".to_string() })
+                    .map_err(|e| GameError::CustomError(e.to_string()))?;
+            }
+            Some(Action::OpenSettings) => {
+                self.scene_channel_s.send(SceneMessage::EnterSceneSettings)
+                    .map_err(|e| GameError::CustomError(e.to_string()))?;
+            }
+            _ => ()
         }
         Ok(())
     }
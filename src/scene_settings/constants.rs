@@ -0,0 +1,19 @@
+use ggez::graphics::Color;
+
+// layout
+pub const SETTINGS_MARGIN: f32 = 100.0;
+pub const SETTINGS_ROWS_TOP: f32 = 220.0;
+pub const SETTINGS_ROW_HEIGHT: f32 = 44.0;
+pub const SETTINGS_COLUMN_GAP: f32 = 40.0; // at least this much between a row's name and its value
+
+// text
+pub const SETTINGS_TITLE_SCALE: f32 = 64.0;
+pub const SETTINGS_SCALE: f32 = 32.0;
+
+// colors
+pub const SETTINGS_BG_COLOR: Color = Color::new(0.1, 0.1, 0.12, 1.0);
+pub const SETTINGS_TEXT_COLOR: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+pub const SETTINGS_HINT_COLOR: Color = Color::new(0.6, 0.6, 0.6, 1.0);
+pub const SETTINGS_SELECTED_COLOR: Color = Color::new(0.25, 0.3, 0.4, 1.0);
+pub const SETTINGS_LISTENING_COLOR: Color = Color::new(1.0, 0.9, 0.3, 1.0);
+pub const SETTINGS_WARNING_COLOR: Color = Color::new(1.0, 0.4, 0.4, 1.0);
@@ -0,0 +1,324 @@
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+use ggez::{
+    event,
+    glam,
+    graphics::{self, Text, TextFragment},
+    input::{mouse::MouseButton, keyboard::{KeyInput, KeyCode}},
+    Context, GameResult, GameError
+};
+
+use super::constants::*;
+
+use crate::constants::*;
+use crate::mainstate::SceneState;
+use crate::helpers::*;
+use crate::keymap::{self, Keymap, Action, Binding, BindError};
+use crate::preferences::{self, Preferences, Theme};
+
+// the preferences on one page, and every action and what it's bound to on another
+// the keys for getting around this screen are fixed, so there's no way to lock yourself out
+pub struct SettingsState {
+    preferences: Preferences,
+    keymap: Keymap,
+    page: Page,
+    selected: usize,
+    listening: bool, // the next key pressed becomes the binding for the selected action
+    message: Option<String>,
+    config_dir: PathBuf,
+    scene_channel_s: mpsc::Sender<SceneMessage>
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Page{
+    Preferences,
+    Controls
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Preference{
+    Resolution,
+    Fullscreen,
+    Vsync,
+    SimulationSpeed,
+    UiScale,
+    Theme
+}
+
+impl SettingsState {
+    pub fn new(ctx: &mut Context, s: mpsc::Sender<SceneMessage>) -> GameResult<SettingsState> {
+        Ok(SettingsState {
+            preferences: preferences::current(),
+            keymap: keymap::current(),
+            page: Page::Preferences,
+            selected: 0,
+            listening: false,
+            message: None,
+            config_dir: ctx.fs.user_config_dir().to_path_buf(),
+            scene_channel_s: s
+        })
+    }
+
+    fn num_rows(&self) -> usize{
+        match self.page{
+            Page::Preferences => Preference::ALL.len(),
+            Page::Controls => Action::ALL.len()
+        }
+    }
+
+    // changes the selected preference to the next or previous choice, and applies it straight away
+    fn change(&mut self, ctx: &mut Context, forward: bool) -> GameResult{
+        let preference = Preference::ALL[self.selected];
+        preference.step(&mut self.preferences, forward);
+        preferences::set(self.preferences);
+        if matches!(preference, Preference::Resolution | Preference::Fullscreen){
+            ctx.gfx.set_mode(self.preferences.window_mode())?;
+        }
+        self.message = self.preferences.save(&self.config_dir).err()
+            .map(|e| format!("Couldn't save the preferences: {}", e));
+        Ok(())
+    }
+
+    fn rebind(&mut self, binding: Binding){
+        let action = Action::ALL[self.selected];
+        match self.keymap.bind(action, binding){
+            Ok(()) => self.save_keymap(),
+            Err(BindError::Taken(other)) => self.message = Some(format!("{} is already used for {}", binding.label(), other.name())),
+            Err(BindError::Reserved) => self.message = Some(format!("{} is reserved and can't be rebound", binding.label()))
+        }
+    }
+
+    fn reset(&mut self){
+        let action = Action::ALL[self.selected];
+        match self.keymap.reset(action){
+            Ok(()) => self.save_keymap(),
+            Err(other) => self.message = Some(format!("The default keys for this are used for {}", other.name()))
+        }
+    }
+
+    // takes effect straight away, and sticks around for next time
+    fn save_keymap(&mut self){
+        keymap::set(self.keymap.clone());
+        self.message = self.keymap.save(&self.config_dir).err()
+            .map(|e| format!("Couldn't save the keymap: {}", e));
+    }
+
+    fn row_at(&self, y: f32) -> Option<usize>{
+        let row = ((y - SETTINGS_ROWS_TOP) / SETTINGS_ROW_HEIGHT).floor();
+        if row >= 0.0 && (row as usize) < self.num_rows() {Some(row as usize)} else {None}
+    }
+
+    // what goes on the left and right of each row
+    fn row_text(&self, i: usize) -> (String, Text){
+        match self.page{
+            Page::Preferences => {
+                let preference = Preference::ALL[i];
+                let value = format!("< {} >", preference.value(&self.preferences));
+                (preference.name().to_string(), Self::text(value, SETTINGS_TEXT_COLOR, SETTINGS_SCALE))
+            }
+            Page::Controls => {
+                let action = Action::ALL[i];
+                let bindings = if i == self.selected && self.listening{
+                    Self::text("press a key...", SETTINGS_LISTENING_COLOR, SETTINGS_SCALE)
+                }else{
+                    let labels: Vec<String> = self.keymap.bindings(action).iter().map(|b| b.label()).collect();
+                    Self::text(labels.join(", "), SETTINGS_TEXT_COLOR, SETTINGS_SCALE)
+                };
+                (action.name().to_string(), bindings)
+            }
+        }
+    }
+
+    // values start halfway across, further right if a name is wider than that, and move left when
+    // the widest value would run off the edge (without going over the names)
+    // the screen is in ui units, so this follows the ui scale as well as the window size
+    fn value_x(rows: &[(String, Text)], screen: graphics::Rect, ctx: &Context) -> GameResult<f32>{
+        let mut widest_name: f32 = 0.0;
+        let mut widest_value: f32 = 0.0;
+        for (name, value) in rows.iter(){
+            widest_name = widest_name.max(Self::text(name.clone(), SETTINGS_TEXT_COLOR, SETTINGS_SCALE).measure(ctx)?.x);
+            widest_value = widest_value.max(value.measure(ctx)?.x);
+        }
+        let after_names = SETTINGS_MARGIN + widest_name + SETTINGS_COLUMN_GAP;
+        let fits = screen.w - SETTINGS_MARGIN - widest_value;
+        Ok((screen.w / 2.0).min(fits).max(after_names))
+    }
+
+    fn text(content: impl Into<String>, color: graphics::Color, scale: f32) -> Text{
+        Text::new(TextFragment::new(content).color(color).scale(scale))
+    }
+}
+
+impl Preference{
+    const ALL: [Preference; 6] = [
+        Preference::Resolution,
+        Preference::Fullscreen,
+        Preference::Vsync,
+        Preference::SimulationSpeed,
+        Preference::UiScale,
+        Preference::Theme
+    ];
+
+    fn name(&self) -> &'static str{
+        match self{
+            Preference::Resolution => "Window size",
+            Preference::Fullscreen => "Fullscreen",
+            Preference::Vsync => "Vsync (after a restart)",
+            Preference::SimulationSpeed => "Simulation speed",
+            Preference::UiScale => "UI scale",
+            Preference::Theme => "Colors"
+        }
+    }
+
+    fn value(&self, prefs: &Preferences) -> String{
+        let on_off = |on: bool| if on {"On".to_string()} else {"Off".to_string()};
+        match self{
+            Preference::Resolution => format!("{}x{}", prefs.resolution.0, prefs.resolution.1),
+            Preference::Fullscreen => on_off(prefs.fullscreen),
+            Preference::Vsync => on_off(prefs.vsync),
+            Preference::SimulationSpeed => format!("{}x", prefs.simulation_speed),
+            Preference::UiScale => format!("{}%", (prefs.ui_scale * 100.0).round()),
+            Preference::Theme => prefs.theme.name().to_string()
+        }
+    }
+
+    fn step(&self, prefs: &mut Preferences, forward: bool){
+        match self{
+            Preference::Resolution => prefs.resolution = cycle(&RESOLUTIONS, prefs.resolution, forward),
+            Preference::Fullscreen => prefs.fullscreen = !prefs.fullscreen,
+            Preference::Vsync => prefs.vsync = !prefs.vsync,
+            Preference::SimulationSpeed => prefs.simulation_speed = cycle(&SIMULATION_SPEEDS, prefs.simulation_speed, forward),
+            Preference::UiScale => prefs.ui_scale = cycle(&UI_SCALES, prefs.ui_scale, forward),
+            Preference::Theme => prefs.theme = cycle(&Theme::ALL, prefs.theme, forward)
+        }
+    }
+}
+
+// the next or previous option, wrapping around
+// values that aren't one of the options (from editing the file by hand) go to the first one
+fn cycle<T: Copy + PartialEq>(options: &[T], current: T, forward: bool) -> T{
+    match options.iter().position(|option| *option == current){
+        Some(i) if forward => options[(i + 1) % options.len()],
+        Some(i) => options[(i + options.len() - 1) % options.len()],
+        None => options[0]
+    }
+}
+
+impl SceneState for SettingsState {
+    fn mouse_click_event(&mut self, ctx: &mut Context, _button: MouseButton, _x: f32, y: f32) -> GameResult{
+        if let Some(row) = self.row_at(y){
+            self.selected = row;
+            self.message = None;
+            match self.page{
+                Page::Preferences => self.change(ctx, true)?,
+                Page::Controls => self.listening = true
+            }
+        }
+        Ok(())
+    }
+
+    fn captures_keys(&self) -> bool{
+        true
+    }
+}
+
+impl event::EventHandler for SettingsState {
+    fn update(&mut self, _ctx: &mut Context) -> GameResult {
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let screen = preferences::screen_rect(ctx);
+        let mut canvas = graphics::Canvas::from_frame(ctx, SETTINGS_BG_COLOR);
+        canvas.set_screen_coordinates(screen);
+
+        // the page that isn't showing is greyed out
+        let (settings_color, controls_color) = match self.page{
+            Page::Preferences => (SETTINGS_TEXT_COLOR, SETTINGS_HINT_COLOR),
+            Page::Controls => (SETTINGS_HINT_COLOR, SETTINGS_TEXT_COLOR)
+        };
+        canvas.draw(
+            &Self::text("Settings", settings_color, SETTINGS_TITLE_SCALE),
+            glam::vec2(SETTINGS_MARGIN, SETTINGS_MARGIN)
+        );
+        let rows: Vec<(String, Text)> = (0..self.num_rows()).map(|i| self.row_text(i)).collect();
+        let value_x = Self::value_x(&rows, screen, ctx)?;
+        canvas.draw(
+            &Self::text("Controls", controls_color, SETTINGS_TITLE_SCALE),
+            glam::vec2(value_x, SETTINGS_MARGIN)
+        );
+
+        for (i, (name, value)) in rows.into_iter().enumerate(){
+            let y = SETTINGS_ROWS_TOP + i as f32 * SETTINGS_ROW_HEIGHT;
+            if i == self.selected{
+                canvas.draw(
+                    &graphics::Mesh::new_rectangle(
+                        ctx,
+                        graphics::DrawMode::fill(),
+                        graphics::Rect::new(SETTINGS_MARGIN / 2.0, y, screen.w - SETTINGS_MARGIN, SETTINGS_ROW_HEIGHT),
+                        SETTINGS_SELECTED_COLOR
+                    )?,
+                    glam::vec2(0.0, 0.0)
+                );
+            }
+            canvas.draw(&Self::text(name, SETTINGS_TEXT_COLOR, SETTINGS_SCALE), glam::vec2(SETTINGS_MARGIN, y));
+            canvas.draw(&value, glam::vec2(value_x, y));
+        }
+
+        let bottom = screen.h - SETTINGS_MARGIN;
+        if let Some(message) = &self.message{
+            canvas.draw(
+                &Self::text(message.clone(), SETTINGS_WARNING_COLOR, SETTINGS_SCALE),
+                glam::vec2(SETTINGS_MARGIN, bottom - 2.0 * SETTINGS_ROW_HEIGHT)
+            );
+        }
+        let hint = match self.page{
+            Page::Preferences => "Up/Down to choose, Left/Right or click to change, Tab for controls, Esc to go back",
+            Page::Controls => "Up/Down to choose, Enter or click to rebind, Delete to reset, Tab for settings, Esc to go back"
+        };
+        canvas.draw(
+            &Self::text(hint, SETTINGS_HINT_COLOR, SETTINGS_SCALE),
+            glam::vec2(SETTINGS_MARGIN, bottom - SETTINGS_ROW_HEIGHT)
+        );
+
+        canvas.finish(ctx)?;
+        Ok(())
+    }
+
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeated: bool) -> GameResult {
+        if self.listening{
+            // escape gets out of rebinding instead of being bound
+            if input.keycode == Some(KeyCode::Escape){
+                self.listening = false;
+            }else if let Some(binding) = Binding::from_input(&input){
+                self.listening = false;
+                self.rebind(binding);
+            }
+            return Ok(());
+        }
+        let rows = self.num_rows();
+        match (self.page, input.keycode){
+            (_, Some(KeyCode::Up)) => self.selected = (self.selected + rows - 1) % rows,
+            (_, Some(KeyCode::Down)) => self.selected = (self.selected + 1) % rows,
+            (_, Some(KeyCode::Tab)) => {
+                self.page = if self.page == Page::Preferences {Page::Controls} else {Page::Preferences};
+                self.selected = 0;
+                self.message = None;
+            }
+            (Page::Preferences, Some(KeyCode::Left)) => self.change(ctx, false)?,
+            (Page::Preferences, Some(KeyCode::Right | KeyCode::Return)) => self.change(ctx, true)?,
+            (Page::Controls, Some(KeyCode::Return)) => {
+                self.listening = true;
+                self.message = None;
+            }
+            (Page::Controls, Some(KeyCode::Delete | KeyCode::Back)) => self.reset(),
+            (_, Some(KeyCode::Escape)) => {
+                self.scene_channel_s.send(SceneMessage::EnterSceneMainMenu)
+                    .map_err(|e| GameError::CustomError(e.to_string()))?;
+            }
+            _ => ()
+        }
+        Ok(())
+    }
+}
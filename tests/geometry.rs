@@ -0,0 +1,261 @@
+// property tests for the block object geometry that the simulation leans on
+// and for moving groups of tiles around while building
+use std::collections::{BTreeSet, HashSet};
+
+use proptest::prelude::*;
+
+use conveyor_chaos::scene_level::block::{Block, BlockObject, BlockObjectMode};
+use conveyor_chaos::scene_level::helpers::{BoardPos, Direction, MovementType};
+use conveyor_chaos::scene_level::tile::{Tile, TileType};
+
+fn pos() -> impl Strategy<Value = BoardPos>{
+    (-6..6, -6..6).prop_map(|(x, y)| BoardPos{x, y})
+}
+
+// the cells of a block object, with no repeats (it doesn't have to be connected)
+// block objects aren't Debug, so proptest generates these and the tests build the block object
+fn blockcells() -> impl Strategy<Value = BTreeSet<(i32, i32)>>{
+    prop::collection::btree_set((-4..4, -4..4), 1..16)
+}
+
+fn blockobject(cells: &BTreeSet<(i32, i32)>) -> BlockObject{
+    let blocks = cells.iter().map(|(x, y)| Block::new(BoardPos{x: *x, y: *y})).collect();
+    BlockObject::from_blocklist(blocks, BlockObjectMode::Processing)
+}
+
+fn cells(bo: &BlockObject) -> BTreeSet<(i32, i32)>{
+    bo.block_locations().into_iter().map(|pos| (pos.x, pos.y)).collect()
+}
+
+// the bounds worked out from scratch, to compare against the cached ones
+fn true_bounds(bo: &BlockObject) -> (BoardPos, BoardPos){
+    let locations = bo.block_locations();
+    let tl = BoardPos{
+        x: locations.iter().map(|pos| pos.x).min().expect("empty block object"),
+        y: locations.iter().map(|pos| pos.y).min().expect("empty block object")
+    };
+    let br = BoardPos{
+        x: locations.iter().map(|pos| pos.x).max().expect("empty block object"),
+        y: locations.iter().map(|pos| pos.y).max().expect("empty block object")
+    };
+    (tl, br)
+}
+
+// tiles aren't Debug either, so this makes the parts of one
+fn tile_parts() -> impl Strategy<Value = (TileType, BoardPos, Direction)>{
+    let tiletypes: Vec<TileType> = TileType::all().collect();
+    let directions = vec![Direction::Right, Direction::Down, Direction::Left, Direction::Up];
+    (prop::sample::select(tiletypes), pos(), prop::sample::select(directions))
+}
+
+fn tile(parts: (TileType, BoardPos, Direction)) -> Tile{
+    Tile::new_directional(parts.0, parts.1, parts.2)
+}
+
+// the moves a group of tiles can make while building
+fn group_movement() -> impl Strategy<Value = MovementType>{
+    prop_oneof![
+        (-4..4, pos()).prop_map(|(quarter_turns, around)| MovementType::Rotation{quarter_turns, around}),
+        (any::<bool>(), pos()).prop_map(|(vertical, around)| MovementType::Mirror{vertical, around})
+    ]
+}
+
+fn tile_state(tile: &Tile) -> (TileType, BoardPos, Direction){
+    (tile.get_type(), tile.get_pos(), tile.get_dir())
+}
+
+#[derive(Clone, Debug)]
+enum Mutation{
+    Translate(i32, i32),
+    RotateCw(BoardPos),
+    RotateCcw(BoardPos),
+    Rotate(i32, BoardPos),
+    Mirror(bool, BoardPos),
+    SplitOff(usize), // split off every nth block
+    Merge(Vec<(i32, i32)>)
+}
+
+fn mutation() -> impl Strategy<Value = Mutation>{
+    prop_oneof![
+        (-3..3, -3..3).prop_map(|(dx, dy)| Mutation::Translate(dx, dy)),
+        pos().prop_map(Mutation::RotateCw),
+        pos().prop_map(Mutation::RotateCcw),
+        (-4..4, pos()).prop_map(|(quarter_turns, around)| Mutation::Rotate(quarter_turns, around)),
+        (any::<bool>(), pos()).prop_map(|(vertical, around)| Mutation::Mirror(vertical, around)),
+        (2..4_usize).prop_map(Mutation::SplitOff),
+        prop::collection::vec((-6..6, -6..6), 1..4).prop_map(Mutation::Merge)
+    ]
+}
+
+fn apply(bo: &mut BlockObject, mutation: &Mutation){
+    match mutation{
+        Mutation::Translate(dx, dy) => bo.translate(*dx, *dy),
+        Mutation::RotateCw(around) => bo.rotate_cw(*around),
+        Mutation::RotateCcw(around) => bo.rotate_ccw(*around),
+        Mutation::Rotate(quarter_turns, around) => bo.rotate(*quarter_turns, *around),
+        Mutation::Mirror(vertical, around) => bo.mirror(*vertical, *around),
+        Mutation::SplitOff(n) => {
+            // always leave the first block behind so bo never ends up empty
+            let positions: Vec<BoardPos> = bo.block_locations().into_iter().skip(1).step_by(*n).collect();
+            bo.split_off(&positions);
+        },
+        Mutation::Merge(extra) => {
+            let blocks = extra.iter().map(|(x, y)| Block::new(BoardPos{x: *x, y: *y})).collect();
+            let mut other = BlockObject::from_blocklist(blocks, BlockObjectMode::Processing);
+            bo.merge(&mut other);
+        }
+    }
+}
+
+proptest!{
+    #[test]
+    fn four_rotations_are_identity(start in blockcells(), around in pos()){
+        let mut bo = blockobject(&start);
+        for _ in 0..4{
+            bo.rotate_cw(around);
+        }
+        prop_assert_eq!(&cells(&bo), &start);
+        for _ in 0..4{
+            bo.rotate_ccw(around);
+        }
+        prop_assert_eq!(&cells(&bo), &start);
+    }
+
+    #[test]
+    fn rotations_undo_each_other(start in blockcells(), quarter_turns in -4..4, around in pos()){
+        let mut bo = blockobject(&start);
+        bo.rotate(quarter_turns, around);
+        bo.rotate(-quarter_turns, around);
+        prop_assert_eq!(cells(&bo), start);
+    }
+
+    #[test]
+    fn mirroring_twice_is_identity(start in blockcells(), vertical in any::<bool>(), around in pos()){
+        let mut bo = blockobject(&start);
+        bo.mirror(vertical, around);
+        bo.mirror(vertical, around);
+        prop_assert_eq!(cells(&bo), start);
+    }
+
+    #[test]
+    fn seam_pieces_cover_every_block_once(
+        start in blockcells(),
+        vert_seams in prop::collection::hash_set(-4..4, 0..4),
+        hori_seams in prop::collection::hash_set(-4..4, 0..4)
+    ){
+        let bo = blockobject(&start);
+        let pieces = bo.seam_pieces(&vert_seams, &hori_seams);
+        let mut seen = HashSet::new();
+        for piece in pieces.iter(){
+            prop_assert!(!piece.is_empty());
+            for pos in piece.iter(){
+                prop_assert!(seen.insert(*pos), "{:?} is in more than one piece", pos);
+            }
+        }
+        let seen: BTreeSet<(i32, i32)> = seen.into_iter().map(|pos| (pos.x, pos.y)).collect();
+        prop_assert_eq!(seen, cells(&bo));
+    }
+
+    #[test]
+    fn split_then_merge_keeps_every_block(
+        start in blockcells(),
+        vert_seams in prop::collection::hash_set(-4..4, 0..4),
+        hori_seams in prop::collection::hash_set(-4..4, 0..4)
+    ){
+        let mut bo = blockobject(&start);
+        let pieces = bo.seam_pieces(&vert_seams, &hori_seams);
+        let mut split: Vec<BlockObject> = pieces.iter().skip(1).map(|piece| bo.split_off(piece)).collect();
+        prop_assert_eq!(cells(&bo), pieces[0].iter().map(|pos| (pos.x, pos.y)).collect::<BTreeSet<_>>());
+
+        for other in split.iter_mut(){
+            bo.merge(other);
+            prop_assert!(other.blocks.is_empty());
+        }
+        prop_assert_eq!(cells(&bo), start);
+        prop_assert_eq!(bo.blocks.len(), cells(&bo).len());
+    }
+
+    #[test]
+    fn cached_bounds_stay_correct(start in blockcells(), mutations in prop::collection::vec(mutation(), 1..12)){
+        let mut bo = blockobject(&start);
+        for mutation in mutations.iter(){
+            apply(&mut bo, mutation);
+            let (tl, br) = true_bounds(&bo);
+            prop_assert_eq!(bo.get_top_left().expect("no blocks"), tl, "top left after {:?}", mutation);
+            prop_assert_eq!(bo.get_bottom_right().expect("no blocks"), br, "bottom right after {:?}", mutation);
+        }
+    }
+
+    #[test]
+    fn exact_overlap_only_matches_the_same_cells(start in blockcells(), dx in -2..2, dy in -2..2){
+        let mut bo = blockobject(&start);
+        let mut other = bo.clone();
+        prop_assert!(bo.exact_overlap(&mut other));
+        prop_assert!(other.exact_overlap(&mut bo));
+
+        other.translate(dx, dy);
+        let same = dx == 0 && dy == 0;
+        prop_assert_eq!(bo.exact_overlap(&mut other), same);
+        prop_assert_eq!(other.exact_overlap(&mut bo), same);
+    }
+
+    #[test]
+    fn overlap_tile_matches_the_block_list(start in blockcells(), pos in pos()){
+        let mut bo = blockobject(&start);
+        let expected = bo.block_locations().contains(&pos);
+        prop_assert_eq!(bo.overlap_tile(pos), expected);
+    }
+
+    #[test]
+    fn turning_tiles_four_times_is_identity(parts in tile_parts(), around in pos()){
+        let tile = tile(parts);
+        let mut turned = tile.clone();
+        for _ in 0..4{
+            turned.apply(MovementType::Rotation{quarter_turns: 1, around});
+        }
+        prop_assert_eq!(tile_state(&turned), tile_state(&tile));
+    }
+
+    #[test]
+    fn mirroring_tiles_twice_is_identity(parts in tile_parts(), vertical in any::<bool>(), around in pos()){
+        let tile = tile(parts);
+        let mut mirrored = tile.clone();
+        mirrored.apply(MovementType::Mirror{vertical, around});
+        mirrored.apply(MovementType::Mirror{vertical, around});
+        prop_assert_eq!(tile_state(&mirrored), tile_state(&tile));
+    }
+
+    // so a conveyor line still runs along itself after it's moved
+    #[test]
+    fn tiles_still_point_at_the_same_neighbour(parts in tile_parts(), movement in group_movement()){
+        let tile = tile(parts);
+        let (dx, dy) = tile.get_dir().to_offset();
+        let neighbour = BoardPos{x: tile.get_x() + dx, y: tile.get_y() + dy};
+
+        let mut moved = tile.clone();
+        moved.apply(movement);
+        let (dx, dy) = moved.get_dir().to_offset();
+        prop_assert_eq!(BoardPos{x: moved.get_x() + dx, y: moved.get_y() + dy}, movement.apply(neighbour));
+    }
+}
+
+#[test]
+fn tiles_with_a_handedness_swap(){
+    let tile = |id: &str| Tile::new(TileType::from_id(id).expect("missing tile type"), BoardPos{x: 0, y: 0});
+    let turn = MovementType::Rotation{quarter_turns: 1, around: BoardPos{x: 0, y: 0}};
+    let mirror = MovementType::Mirror{vertical: true, around: BoardPos{x: 0, y: 0}};
+    for (id, turned, mirrored) in [
+        ("rot_tile_cw", "rot_tile_cw", "rot_tile_ccw"),
+        ("rot_tile_ccw", "rot_tile_ccw", "rot_tile_cw"),
+        ("rot_tile_180", "rot_tile_180", "rot_tile_180"),
+        ("mirror_tile_hori", "mirror_tile_vert", "mirror_tile_hori"),
+        ("mirror_tile_vert", "mirror_tile_hori", "mirror_tile_vert")
+    ]{
+        let mut moved = tile(id);
+        moved.apply(turn);
+        assert_eq!(moved.get_type().kind().id, turned, "turning {}", id);
+        let mut moved = tile(id);
+        moved.apply(mirror);
+        assert_eq!(moved.get_type().kind().id, mirrored, "mirroring {}", id);
+    }
+}
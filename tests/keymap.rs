@@ -0,0 +1,89 @@
+// loading, saving and rebinding keys, without opening a window
+use ggez::input::keyboard::KeyCode;
+
+use conveyor_chaos::keymap::{Keymap, Action, Binding, BindError};
+
+fn key(key: KeyCode) -> Binding{
+    Binding{key, ctrl: false, shift: false}
+}
+
+#[test]
+fn defaults_have_no_conflicts(){
+    let keymap = Keymap::default();
+    assert!(keymap.conflicts().is_empty());
+    for action in Action::ALL{
+        assert!(!keymap.bindings(action).is_empty(), "{} has no keys", action.name());
+    }
+}
+
+#[test]
+fn modifiers_pick_different_actions(){
+    let keymap = Keymap::default();
+    assert_eq!(keymap.action(key(KeyCode::X)), Some(Action::FlipHorizontal));
+    assert_eq!(keymap.action(Binding{key: KeyCode::X, ctrl: false, shift: true}), Some(Action::FlipVertical));
+    assert_eq!(keymap.action(Binding{key: KeyCode::X, ctrl: true, shift: false}), Some(Action::Cut));
+    assert_eq!(keymap.action(Binding{key: KeyCode::X, ctrl: true, shift: true}), None);
+}
+
+#[test]
+fn saved_keymaps_load_the_same(){
+    let mut keymap = Keymap::default();
+    keymap.bind(Action::RotateCw, key(KeyCode::E)).unwrap();
+    let dir = std::env::temp_dir().join(format!("conveyor-chaos-keymap-{}", std::process::id()));
+    keymap.save(&dir).unwrap();
+    assert_eq!(Keymap::load(&dir).unwrap(), keymap);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_actions_keep_their_defaults(){
+    let keymap = Keymap::from_json(r#"{"bindings": {"rotate_cw": [{"key": "E"}]}}"#).unwrap();
+    assert_eq!(keymap.bindings(Action::RotateCw), &[key(KeyCode::E)]);
+    assert_eq!(keymap.bindings(Action::Quit), Keymap::default().bindings(Action::Quit));
+}
+
+#[test]
+fn conflicting_files_are_rejected(){
+    let json = r#"{"bindings": {"rotate_cw": [{"key": "M"}]}}"#;
+    assert!(Keymap::from_json(json).is_err());
+}
+
+#[test]
+fn rebinding_refuses_keys_that_are_taken(){
+    let mut keymap = Keymap::default();
+    assert_eq!(keymap.bind(Action::RotateCw, key(KeyCode::M)), Err(BindError::Taken(Action::MoveSelection)));
+    assert_eq!(keymap.bindings(Action::RotateCw), &[key(KeyCode::R)]);
+
+    // once M is free it can be taken, and the defaults can't come back while R is used
+    keymap.bind(Action::MoveSelection, key(KeyCode::N)).unwrap();
+    keymap.bind(Action::RotateCw, key(KeyCode::M)).unwrap();
+    keymap.bind(Action::Snapshot, key(KeyCode::R)).unwrap();
+    assert_eq!(keymap.reset(Action::RotateCw), Err(Action::Snapshot));
+    assert!(keymap.conflicts().is_empty());
+}
+
+#[test]
+fn reserved_keys_cant_be_bound(){
+    let mut keymap = Keymap::default();
+    for reserved in [KeyCode::Key1, KeyCode::Numpad0, KeyCode::F1, KeyCode::F11, KeyCode::Up, KeyCode::Tab, KeyCode::Escape]{
+        assert_eq!(keymap.bind(Action::RotateCw, key(reserved)), Err(BindError::Reserved), "{:?}", reserved);
+    }
+    // modifiers don't help, the sidebar reads the key by itself
+    assert_eq!(keymap.bind(Action::RotateCw, Binding{key: KeyCode::Key3, ctrl: true, shift: false}), Err(BindError::Reserved));
+    assert_eq!(keymap.bindings(Action::RotateCw), &[key(KeyCode::R)]);
+
+    // the actions that have them by default can still go back to them
+    keymap.bind(Action::Quit, key(KeyCode::Q)).unwrap();
+    keymap.bind(Action::Quit, key(KeyCode::Escape)).unwrap();
+    keymap.bind(Action::RotateCw, key(KeyCode::F12)).unwrap_err();
+    keymap.bind(Action::Snapshot, key(KeyCode::P)).unwrap();
+    keymap.bind(Action::RotateCw, key(KeyCode::F12)).unwrap();
+}
+
+#[test]
+fn files_with_reserved_keys_are_rejected(){
+    assert!(Keymap::from_json(r#"{"bindings": {"rotate_cw": [{"key": "Key5"}]}}"#).is_err());
+    assert!(Keymap::from_json(r#"{"bindings": {"zoom_in": [{"key": "F4"}]}}"#).is_err());
+    assert!(Keymap::from_json(r#"{"bindings": {"copy": [{"key": "Return", "ctrl": true}]}}"#).is_err());
+    assert!(Keymap::from_json(r#"{"bindings": {"delete_selection": [{"key": "Back"}]}}"#).is_ok());
+}
@@ -0,0 +1,34 @@
+// loading and saving preferences, without opening a window
+use conveyor_chaos::preferences::{Preferences, Theme};
+
+#[test]
+fn saved_preferences_load_the_same(){
+    let preferences = Preferences{
+        resolution: (1280.0, 720.0),
+        fullscreen: true,
+        simulation_speed: 4.0,
+        theme: Theme::Night,
+        ..Preferences::default()
+    };
+    let dir = std::env::temp_dir().join(format!("conveyor-chaos-preferences-{}", std::process::id()));
+    preferences.save(&dir).unwrap();
+    assert_eq!(Preferences::load(&dir).unwrap(), preferences);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_preferences_keep_their_defaults(){
+    let preferences = Preferences::from_json(r#"{"theme": "blueprint", "ui_scale": 1.5}"#).unwrap();
+    assert_eq!(preferences.theme, Theme::Blueprint);
+    assert_eq!(preferences.ui_scale, 1.5);
+    assert_eq!(preferences.resolution, Preferences::default().resolution);
+    // files saved when there was a volume setting still load
+    assert!(Preferences::from_json(r#"{"volume": 0.5}"#).is_ok());
+}
+
+#[test]
+fn bad_preferences_are_rejected(){
+    assert!(Preferences::from_json(r#"{"simulation_speed": 0}"#).is_err());
+    assert!(Preferences::from_json(r#"{"resolution": [-1, 720]}"#).is_err());
+    assert!(Preferences::from_json(r#"{"theme": "neon"}"#).is_err());
+}
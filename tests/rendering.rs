@@ -0,0 +1,107 @@
+// golden image tests for how block objects get drawn, mostly the rounded corners that depend on each block's neighbours
+// this needs a gpu and a display, so it runs as a plain main
+// not being able to open a window is a failure, SKIP_RENDERING_TESTS=1 is for machines that can't
+// UPDATE_GOLDEN=1 cargo test --test rendering writes the images in tests/golden, a missing one fails otherwise
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
+
+use ggez::Context;
+
+use conveyor_chaos::helpers::hidden_context;
+use conveyor_chaos::scene_level::block::{Block, BlockObject, BlockObjectMode};
+use conveyor_chaos::scene_level::board::{Board, BoardCanvas, BoardState};
+use conveyor_chaos::scene_level::helpers::*;
+use conveyor_chaos::scene_level::level::LevelMode;
+
+const TILE_SIZE: f32 = 32.0;
+// how far off a channel can be before it counts as different, gpus don't all round the same way
+const TOLERANCE: u8 = 2;
+
+// each case is one output block object drawn on its own, as rows of text with # for a block
+const CASES: [(&str, &[&str]); 6] = [
+    ("single", &["#"]),
+    ("square", &["##", "##"]),
+    ("l_shape", &["#.", "#.", "##"]),
+    ("plus", &[".#.", "###", ".#."]),
+    ("ring", &["###", "#.#", "###"]),
+    ("diagonal", &["#.", ".#"])
+];
+
+fn board(rows: &[&str]) -> BoardState{
+    let blocks = rows.iter().enumerate().flat_map(|(y, row)| {
+        row.chars().enumerate()
+            .filter(|(_, c)| *c == '#')
+            .map(move |(x, _)| Block::new(BoardPos{x: x as i32, y: y as i32}))
+    }).collect();
+    let mut blockobject = BlockObject::from_blocklist(blocks, BlockObjectMode::Output);
+    blockobject.id = 1;
+
+    let solution = SerializedSolution{
+        level: "golden".to_string(),
+        blockobjects: vec![SerializedPlacement{id: 1, pos: BoardPos{x: 1, y: 1}, quarter_turns: 0}],
+        tiles: vec![]
+    };
+    let mut state = BoardState::new();
+    state.load_solution(&solution, &[blockobject]).expect("failed to place block object");
+    state
+}
+
+// Err describes what went wrong
+fn check(ctx: &mut Context, name: &str, rows: &[&str]) -> Result<(), String>{
+    let golden = Path::new("tests/golden").join(name).with_extension("png");
+    let actual = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name).with_extension("png");
+    let (columns, height) = (rows[0].len() as u32 + 2, rows.len() as u32 + 2);
+    let view = BoardCanvas::viewport(BoardPos{x: 0, y: 0}, columns, height, TILE_SIZE);
+    Board::render_png(ctx, &mut board(rows), &view, &LevelMode::Building, 1.0, &actual)
+        .map_err(|e| format!("failed to render: {}", e))?;
+
+    if env::var_os("UPDATE_GOLDEN").is_some(){
+        std::fs::create_dir_all("tests/golden").map_err(|e| e.to_string())?;
+        std::fs::copy(&actual, &golden).map_err(|e| e.to_string())?;
+        println!("{}: wrote {}", name, golden.display());
+        return Ok(());
+    }
+
+    if !golden.exists(){
+        return Err(format!("there's no {}, UPDATE_GOLDEN=1 writes it", golden.display()));
+    }
+    let expected = image::open(&golden).map_err(|e| e.to_string())?.to_rgba8();
+    let got = image::open(&actual).map_err(|e| e.to_string())?.to_rgba8();
+    if expected.dimensions() != got.dimensions(){
+        return Err(format!("is {:?} but the golden image is {:?}", got.dimensions(), expected.dimensions()));
+    }
+    let different = expected.pixels().zip(got.pixels())
+        .filter(|(a, b)| a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > TOLERANCE))
+        .count();
+    if different > 0{
+        return Err(format!("{} pixels differ, see {}", different, actual.display()));
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode{
+    let (mut ctx, _event_loop) = match hidden_context(){
+        Ok(context) => context,
+        Err(e) if env::var_os("SKIP_RENDERING_TESTS").is_some() => {
+            println!("skipping rendering tests: {}", e);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            println!("rendering tests FAILED: {}, set SKIP_RENDERING_TESTS=1 to skip them", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut failed = false;
+    for (name, rows) in CASES{
+        match check(&mut ctx, name, rows){
+            Ok(()) => println!("{} ... ok", name),
+            Err(e) => {
+                println!("{} ... FAILED: {}", name, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {ExitCode::FAILURE} else {ExitCode::SUCCESS}
+}
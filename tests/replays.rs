@@ -0,0 +1,113 @@
+// checks that replays rebuild exactly what the simulation did, using the solutions in tests/solutions
+use std::fs;
+
+use conveyor_chaos::scene_level::board::BoardState;
+use conveyor_chaos::scene_level::helpers::*;
+use conveyor_chaos::scene_level::level::LevelState;
+use conveyor_chaos::scene_level::replay::*;
+
+const MAX_TICKS: usize = 500;
+
+fn solutions() -> Vec<(String, SerializedSolution)>{
+    let mut out = vec![];
+    for entry in fs::read_dir("tests/solutions").expect("missing tests/solutions"){
+        let path = entry.expect("failed to read tests/solutions").path();
+        let contents = fs::read_to_string(&path).expect("failed to read solution");
+        // the expectations in these files get ignored
+        let solution: SerializedSolution = serde_json::from_str(&contents)
+            .unwrap_or_else(|e| panic!("failed to parse {}: {}", path.display(), e));
+        out.push((path.display().to_string(), solution));
+    }
+    out.sort_by(|a, b| a.0.cmp(&b.0));
+    out
+}
+
+// the active block objects after every tick that finished without an error
+fn live_frames(solution: &SerializedSolution) -> Vec<Vec<Vec<BoardPos>>>{
+    let (blockobjects, rules) = LevelState::load_level(&solution.level).expect("failed to load level");
+    let mut state = BoardState::new();
+    state.set_rules(rules);
+    state.load_solution(solution, &blockobjects).expect("failed to load solution");
+    state.process_start().expect("failed to start");
+
+    let mut frames = vec![];
+    while frames.len() < MAX_TICKS{
+        match state.process_step(){
+            Ok(false) => frames.push(state.get_active_blocks()),
+            _ => break
+        }
+    }
+    frames
+}
+
+#[test]
+fn playback_matches_the_simulation(){
+    for (path, solution) in solutions(){
+        let replay = Replay::simulate(solution.clone(), MAX_TICKS).expect("failed to simulate");
+        let played = replay.frames().unwrap_or_else(|e| panic!("{}: {}", path, e));
+        let live = live_frames(&solution);
+        for (tick, frame) in live.iter().enumerate(){
+            assert_eq!(&played[tick], frame, "{} differs on tick {}", path, tick);
+        }
+    }
+}
+
+#[test]
+fn simulating_twice_gives_the_same_replay(){
+    for (path, solution) in solutions(){
+        let old = Replay::simulate(solution.clone(), MAX_TICKS).expect("failed to simulate");
+        // go through json like a saved replay would
+        let old: Replay = serde_json::from_str(&serde_json::to_string(&old).expect("failed to write replay"))
+            .expect("failed to read replay");
+        let new = Replay::simulate(solution, MAX_TICKS).expect("failed to simulate");
+        if let Some(divergence) = old.diverge(&new).expect("failed to compare replays"){
+            panic!("{}: {}", path, divergence);
+        }
+    }
+}
+
+#[test]
+fn changed_events_are_found(){
+    for (path, solution) in solutions(){
+        let new = Replay::simulate(solution.clone(), MAX_TICKS).expect("failed to simulate");
+        let mut old = Replay::simulate(solution, MAX_TICKS).expect("failed to simulate");
+
+        // pretend the old rules moved the first thing that moved the other way
+        let moved = old.ticks.iter_mut().enumerate().find_map(|(tick, events)| {
+            events.iter_mut().find_map(|event| match event{
+                ReplayEvent::Move{movement: MovementType::Translation(dir), ..} => {
+                    *dir = dir.clockwise().clockwise();
+                    Some(tick)
+                },
+                _ => None
+            })
+        });
+        if let Some(tick) = moved{
+            let divergence = old.diverge(&new).expect("failed to compare replays")
+                .unwrap_or_else(|| panic!("{}: changed move wasn't noticed", path));
+            assert_eq!(divergence.tick, tick, "{}", path);
+        }
+    }
+}
+
+#[test]
+fn pruning_keeps_the_newest_replays(){
+    let dir = std::env::temp_dir().join(format!("conveyor-chaos-replays-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let start = std::time::SystemTime::now();
+    for i in 0..5{
+        let path = dir.join(format!("level-{}.json", i));
+        fs::write(&path, "{}").unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(start + std::time::Duration::from_secs(i)).unwrap();
+    }
+    fs::write(dir.join("notes.txt"), "not a replay").unwrap();
+
+    Replay::prune(&dir, 2).unwrap();
+    let mut left: Vec<String> = fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    left.sort();
+    assert_eq!(left, ["level-3.json", "level-4.json", "notes.txt"]);
+    fs::remove_dir_all(dir).unwrap();
+}
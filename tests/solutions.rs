@@ -0,0 +1,141 @@
+// runs recorded solutions through the simulation without opening a window
+// each file in tests/solutions is a solution plus what is supposed to happen when it runs
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use conveyor_chaos::scene_level::board::BoardState;
+use conveyor_chaos::scene_level::helpers::*;
+use conveyor_chaos::scene_level::level::LevelState;
+use conveyor_chaos::scene_level::tile::{Tile, TileType};
+
+// nothing in the test levels should take anywhere near this long
+const MAX_TICKS: i32 = 500;
+
+#[derive(Deserialize)]
+struct RecordedSolution{
+    #[serde(flatten)]
+    solution: SerializedSolution,
+    expect: Expectation
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Expectation{
+    Victory{ticks: i32},
+    Error{kind: String, tick: i32, locations: Vec<BoardPos>}
+}
+
+fn error_kind(sim_err: &SimulationError) -> &'static str{
+    match sim_err{
+        SimulationError::MultiPivotRotation{..} => "multi_pivot_rotation",
+        SimulationError::MultiAxisMirror{..} => "multi_axis_mirror",
+        SimulationError::ConflictingPush{..} => "conflicting_push",
+        SimulationError::Collision{..} => "collision",
+        SimulationError::OutputOverflow{..} => "output_overflow",
+        SimulationError::MissingTeleporterExit{..} => "missing_teleporter_exit",
+        SimulationError::Stalled{..} => "stalled",
+        SimulationError::OutOfBounds{..} => "out_of_bounds",
+        SimulationError::Internal(_) => "internal"
+    }
+}
+
+fn run(solution: &SerializedSolution) -> Expectation{
+    let (blockobjects, rules) = LevelState::load_level(&solution.level)
+        .unwrap_or_else(|e| panic!("failed to load level {}: {}", solution.level, e));
+    let mut state = BoardState::new();
+    state.set_rules(rules);
+    state.load_solution(solution, &blockobjects).expect("failed to load solution");
+    state.process_start().expect("failed to start");
+
+    while state.get_game_ticks() < MAX_TICKS{
+        match state.process_step(){
+            Ok(true) => return Expectation::Victory{ticks: state.get_game_ticks()},
+            Ok(false) => (),
+            Err(sim_err) => {
+                let mut locations = sim_err.relevant_locations();
+                locations.sort_by_key(|pos| (pos.x, pos.y));
+                locations.dedup();
+                return Expectation::Error{
+                    kind: error_kind(&sim_err).to_string(),
+                    tick: sim_err.tick().unwrap_or(-1),
+                    locations
+                };
+            }
+        }
+    }
+    panic!("{} didn't finish in {} ticks", solution.level, MAX_TICKS);
+}
+
+fn recorded_solutions() -> Vec<(String, RecordedSolution)>{
+    let mut out = vec![];
+    for entry in fs::read_dir("tests/solutions").expect("missing tests/solutions"){
+        let path = entry.expect("failed to read tests/solutions").path();
+        let contents = fs::read_to_string(&path).expect("failed to read solution");
+        let recorded: RecordedSolution = serde_json::from_str(&contents)
+            .unwrap_or_else(|e| panic!("failed to parse {}: {}", path.display(), e));
+        out.push((path.display().to_string(), recorded));
+    }
+    out.sort_by(|a, b| a.0.cmp(&b.0));
+    out
+}
+
+#[test]
+fn every_level_loads(){
+    for entry in fs::read_dir("levels").expect("missing levels"){
+        let path = entry.expect("failed to read levels").path();
+        let name = path.file_stem().and_then(|stem| stem.to_str()).expect("bad level file name");
+        let (blockobjects, _) = LevelState::load_level(name)
+            .unwrap_or_else(|e| panic!("failed to load level {}: {}", name, e));
+        assert!(!blockobjects.is_empty(), "level {} has no block objects", name);
+    }
+}
+
+#[test]
+fn every_solution_is_for_a_real_level(){
+    for (path, recorded) in recorded_solutions(){
+        let level_path = Path::new("levels").join(&recorded.solution.level).with_extension("json");
+        assert!(level_path.exists(), "{} is for missing level {}", path, recorded.solution.level);
+    }
+}
+
+#[test]
+fn recorded_solutions_behave_the_same(){
+    let mut failures = vec![];
+    for (path, recorded) in recorded_solutions(){
+        let actual = run(&recorded.solution);
+        if actual != recorded.expect{
+            failures.push(format!("{}\n  expected {:?}\n  got      {:?}", path, recorded.expect, actual));
+        }
+    }
+    assert!(failures.is_empty(), "recorded solutions changed behaviour:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn priority_levels_the_level_doesnt_have_are_rejected(){
+    let (blockobjects, rules) = LevelState::load_level("Testlevel4").expect("failed to load Testlevel4");
+    let solution = |level: i32| -> SerializedSolution{
+        serde_json::from_str(&format!(
+            r#"{{"level": "Testlevel4", "blockobjects": [], "tiles": [{{"type": "push_tile", "x": 0, "y": 0, "priority_level": {}}}]}}"#,
+            level
+        )).unwrap()
+    };
+    // Testlevel4 has 3 push levels
+    for (level, ok) in [(0, false), (1, true), (3, true), (4, false), (-2, false)]{
+        let mut state = BoardState::new();
+        state.set_rules(rules.clone());
+        assert_eq!(state.load_solution(&solution(level), &blockobjects).is_ok(), ok, "priority level {}", level);
+    }
+}
+
+#[test]
+fn priority_levels_are_clamped_to_the_ones_there_are(){
+    let mut tile = Tile::new(TileType::from_id("push_tile").unwrap(), BoardPos{x: 0, y: 0});
+    tile.set_priority_level(0, 3);
+    assert_eq!(tile.get_priority_level(), 1);
+    tile.set_priority_level(7, 3);
+    assert_eq!(tile.get_priority_level(), 3);
+    tile.set_priority_level(2, 3);
+    assert_eq!(tile.get_priority_level(), 2);
+}